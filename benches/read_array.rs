@@ -0,0 +1,35 @@
+use std::io::Cursor;
+use binary_interpreter::binary_reader::BinaryReader;
+use byteorder::{ReadBytesExt, BE};
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+
+const COUNT: usize = 1_000_000;
+
+fn vertex_buffer(c: &mut Criterion) {
+    let data: Vec<u8> = (0..COUNT as u32).flat_map(|i| (i as f32).to_be_bytes()).collect();
+    let mut group = c.benchmark_group("1M f32 BE");
+    group.throughput(Throughput::Bytes(data.len() as u64));
+
+    group.bench_function("read_f32 loop", |b| {
+        b.iter(|| {
+            let mut cursor = Cursor::new(black_box(&data));
+            let mut values = Vec::with_capacity(COUNT);
+            for _ in 0..COUNT {
+                values.push(cursor.read_f32::<BE>().unwrap());
+            }
+            values
+        })
+    });
+
+    group.bench_function("read_array", |b| {
+        b.iter(|| {
+            let mut cursor = Cursor::new(black_box(&data));
+            cursor.read_array::<f32, BE>(COUNT).unwrap()
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, vertex_buffer);
+criterion_main!(benches);
@@ -0,0 +1,234 @@
+use std::fmt;
+use std::iter::Peekable;
+use std::ops::Range;
+use std::str::{CharIndices, FromStr};
+use crate::error::InterpreterError;
+
+/// Size of a pointer in the target being read.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PointerWidth {
+    U32,
+    U64,
+}
+
+impl PointerWidth {
+    pub fn bytes(&self) -> usize {
+        match self {
+            PointerWidth::U32 => 4,
+            PointerWidth::U64 => 8,
+        }
+    }
+}
+
+/// Translates between positions in a stream and the virtual addresses they are loaded at.
+pub trait AddressMap {
+    fn to_offset(&self, va: u64) -> Option<u64>;
+    fn to_va(&self, offset: u64) -> Option<u64>;
+}
+
+/// The stream is addressed by virtual address already, e.g. a process or a VA-mapped reader.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Identity;
+
+impl AddressMap for Identity {
+    fn to_offset(&self, va: u64) -> Option<u64> {
+        Some(va)
+    }
+
+    fn to_va(&self, offset: u64) -> Option<u64> {
+        Some(offset)
+    }
+}
+
+/// A location as both a virtual address and, if it is backed by the stream, a stream offset.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Address {
+    pub va: u64,
+    pub offset: Option<u64>,
+}
+
+impl Address {
+    pub fn from_va<M: AddressMap + ?Sized>(va: u64, map: &M) -> Address {
+        Address { va, offset: map.to_offset(va) }
+    }
+}
+
+/// A loaded image, such as an executable or a library mapped into a process.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Module {
+    pub name: String,
+    pub range: Range<u64>,
+}
+
+impl Module {
+    /// Finds the module containing `address`.
+    pub fn containing(modules: &[Module], address: u64) -> Option<&Module> {
+        modules.iter().find(|m| m.range.contains(&address))
+    }
+
+    /// Groups mapped regions by backing path into modules spanning from their lowest to their highest
+    /// address. Modules are named after the file name of their path.
+    pub fn group<'a, I>(regions: I) -> Vec<Module>
+    where
+        I: IntoIterator<Item = (&'a str, Range<u64>)>,
+    {
+        let mut modules: Vec<(&str, Module)> = Vec::new();
+        for (path, range) in regions {
+            match modules.iter_mut().find(|(p, _)| *p == path) {
+                Some((_, module)) => {
+                    module.range.start = module.range.start.min(range.start);
+                    module.range.end = module.range.end.max(range.end);
+                }
+                None => {
+                    let name = path.rsplit(['/', '\\']).next().unwrap_or(path);
+                    modules.push((path, Module { name: name.to_string(), range }));
+                }
+            }
+        }
+        modules.into_iter().map(|(_, module)| module).collect()
+    }
+
+    /// Finds a module by name, ignoring ASCII case as Windows does.
+    pub fn named<'a>(modules: &'a [Module], name: &str) -> Option<&'a Module> {
+        modules.iter().find(|m| m.name.eq_ignore_ascii_case(name))
+    }
+}
+
+/// A pointer chain such as `[["game.exe"+0x10]+0x8]+0x40`. Square brackets dereference a pointer.
+///
+/// Numbers are hexadecimal, with or without `0x`. Anything else is a symbol, usually a module name,
+/// resolved when the expression is evaluated. Quote a symbol that would otherwise read as a number.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum PointerExpr {
+    Const(i64),
+    Symbol(String),
+    Deref(Box<PointerExpr>),
+    Add(Box<PointerExpr>, Box<PointerExpr>),
+    Sub(Box<PointerExpr>, Box<PointerExpr>),
+}
+
+impl PointerExpr {
+    /// Builds `[[base+o0]+o1]+o2` from a base and a list of offsets. Every offset but the last
+    /// is followed by a dereference.
+    pub fn chain(base: PointerExpr, offsets: &[i64]) -> PointerExpr {
+        let mut expr = base;
+        for (i, &offset) in offsets.iter().enumerate() {
+            expr = match offset {
+                0 => expr,
+                o if o < 0 => PointerExpr::Sub(Box::new(expr), Box::new(PointerExpr::Const(o.wrapping_neg()))),
+                o => PointerExpr::Add(Box::new(expr), Box::new(PointerExpr::Const(o))),
+            };
+            if i + 1 != offsets.len() {
+                expr = PointerExpr::Deref(Box::new(expr));
+            }
+        }
+        expr
+    }
+
+    /// Evaluates the expression, calling `resolve` for symbols and `deref` to read pointers.
+    pub fn eval<S, D>(&self, resolve: &S, deref: &mut D) -> Result<u64, InterpreterError>
+    where
+        S: Fn(&str) -> Option<u64> + ?Sized,
+        D: FnMut(u64) -> Result<u64, InterpreterError> + ?Sized,
+    {
+        Ok(match self {
+            PointerExpr::Const(value) => *value as u64,
+            PointerExpr::Symbol(name) => resolve(name).ok_or_else(|| InterpreterError::UnknownSymbol(name.clone()))?,
+            PointerExpr::Deref(inner) => {
+                let address = inner.eval(resolve, deref)?;
+                deref(address)?
+            }
+            PointerExpr::Add(a, b) => a.eval(resolve, deref)?.wrapping_add(b.eval(resolve, deref)?),
+            PointerExpr::Sub(a, b) => a.eval(resolve, deref)?.wrapping_sub(b.eval(resolve, deref)?),
+        })
+    }
+}
+
+fn bad_expr(message: &str, expr: &str) -> InterpreterError {
+    InterpreterError::BadPointerExpr(format!("{} in `{}`", message, expr))
+}
+
+fn parse_sum(chars: &mut Peekable<CharIndices>, expr: &str) -> Result<PointerExpr, InterpreterError> {
+    let mut lhs = parse_term(chars, expr)?;
+    loop {
+        skip_spaces(chars);
+        match chars.peek().map(|&(_, c)| c) {
+            Some('+') => {
+                chars.next();
+                lhs = PointerExpr::Add(Box::new(lhs), Box::new(parse_term(chars, expr)?));
+            }
+            Some('-') => {
+                chars.next();
+                lhs = PointerExpr::Sub(Box::new(lhs), Box::new(parse_term(chars, expr)?));
+            }
+            _ => return Ok(lhs),
+        }
+    }
+}
+
+fn skip_spaces(chars: &mut Peekable<CharIndices>) {
+    while chars.next_if(|&(_, c)| c.is_whitespace()).is_some() {}
+}
+
+fn parse_term(chars: &mut Peekable<CharIndices>, expr: &str) -> Result<PointerExpr, InterpreterError> {
+    skip_spaces(chars);
+    match chars.next() {
+        Some((_, '[')) => {
+            let inner = parse_sum(chars, expr)?;
+            skip_spaces(chars);
+            match chars.next() {
+                Some((_, ']')) => Ok(PointerExpr::Deref(Box::new(inner))),
+                _ => Err(bad_expr("missing `]`", expr)),
+            }
+        }
+        Some((_, '"')) => {
+            let mut name = String::new();
+            for (_, c) in chars.by_ref() {
+                if c == '"' {
+                    return Ok(PointerExpr::Symbol(name));
+                }
+                name.push(c);
+            }
+            Err(bad_expr("missing closing quote", expr))
+        }
+        Some((start, c)) if c.is_alphanumeric() || c == '_' || c == '.' => {
+            let mut end = start + c.len_utf8();
+            while let Some((i, c)) = chars.next_if(|&(_, c)| c.is_alphanumeric() || c == '_' || c == '.') {
+                end = i + c.len_utf8();
+            }
+            let token = &expr[start..end];
+            let digits = token.strip_prefix("0x").or_else(|| token.strip_prefix("0X")).unwrap_or(token);
+            match u64::from_str_radix(digits, 16) {
+                Ok(value) => Ok(PointerExpr::Const(value as i64)),
+                Err(_) => Ok(PointerExpr::Symbol(token.to_string())),
+            }
+        }
+        _ => Err(bad_expr("expected a number, symbol or `[`", expr)),
+    }
+}
+
+impl FromStr for PointerExpr {
+    type Err = InterpreterError;
+
+    fn from_str(expr: &str) -> Result<PointerExpr, InterpreterError> {
+        let mut chars = expr.char_indices().peekable();
+        let parsed = parse_sum(&mut chars, expr)?;
+        skip_spaces(&mut chars);
+        if chars.peek().is_some() {
+            return Err(bad_expr("unexpected trailing input", expr));
+        }
+        Ok(parsed)
+    }
+}
+
+impl fmt::Display for PointerExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PointerExpr::Const(value) => write!(f, "0x{:X}", value),
+            PointerExpr::Symbol(name) => write!(f, "\"{}\"", name),
+            PointerExpr::Deref(inner) => write!(f, "[{}]", inner),
+            PointerExpr::Add(a, b) => write!(f, "{}+{}", a, b),
+            PointerExpr::Sub(a, b) => write!(f, "{}-{}", a, b),
+        }
+    }
+}
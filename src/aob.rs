@@ -0,0 +1,251 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::ops::Range;
+use std::str::FromStr;
+use byteorder::ByteOrder;
+use crate::binary_reader::BinaryPeeker;
+use crate::error::InterpreterError;
+use crate::pod::{EndianPod, as_bytes, needs_swap};
+
+const CHUNK_SIZE: u64 = 1024 * 1024;
+
+/// An array-of-bytes signature, parsed from IDA/Cheat Engine style strings such as
+/// `48 8B 05 ?? ?? ?? ?? 48 85 C0`.
+///
+/// Each token is a hex byte, `?`/`??` for any byte, or a byte with one nibble replaced by `?` (`4?`).
+/// A `|` before a token marks the capture offset, the position reported as `Match::capture`, e.g.
+/// `48 8B 05 | ?? ?? ?? ??` captures the displacement.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Pattern {
+    bytes: Vec<u8>,
+    masks: Vec<u8>,
+    capture: usize,
+    /// Index and value of the first fully specified byte, used to skip ahead quickly.
+    anchor: Option<(usize, u8)>,
+}
+
+/// A place where a `Pattern` matched.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Match {
+    pub offset: u64,
+    /// `offset` plus the pattern's capture offset.
+    pub capture: u64,
+}
+
+fn parse_nibble(chr: char, token: &str) -> Result<(u8, u8), InterpreterError> {
+    match chr {
+        '?' => Ok((0, 0)),
+        _ => chr.to_digit(16)
+            .map(|d| (d as u8, 0xF))
+            .ok_or_else(|| InterpreterError::BadPattern(format!("invalid token `{}`", token))),
+    }
+}
+
+impl Pattern {
+    pub fn new(mut bytes: Vec<u8>, masks: Vec<u8>, capture: usize) -> Result<Pattern, InterpreterError> {
+        if bytes.is_empty() || bytes.len() != masks.len() {
+            return Err(InterpreterError::BadPattern("pattern needs as many masks as bytes, and at least one".to_string()));
+        }
+        if capture > bytes.len() {
+            return Err(InterpreterError::BadPattern(format!("capture offset {} is past the end of the pattern", capture)));
+        }
+        // Matching compares masked haystack bytes against these, so wildcard bits must be clear.
+        for (byte, &mask) in bytes.iter_mut().zip(&masks) {
+            *byte &= mask;
+        }
+        let anchor = masks.iter().position(|&m| m == 0xFF).map(|i| (i, bytes[i]));
+        Ok(Pattern { bytes, masks, capture, anchor })
+    }
+
+    /// A pattern matching exactly `bytes`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Pattern, InterpreterError> {
+        Pattern::new(bytes.to_vec(), vec![0xFF; bytes.len()], 0)
+    }
+
+    /// A pattern matching `value` stored in `E` order. Fails for zero-sized types, which have no bytes to match.
+    pub fn from_value<T: EndianPod, E: ByteOrder>(value: T) -> Result<Pattern, InterpreterError> {
+        let mut value = value;
+        if needs_swap::<E>() {
+            value.swap_bytes();
+        }
+        let bytes = as_bytes(std::slice::from_ref(&value)).to_vec();
+        let masks = vec![0xFF; bytes.len()];
+        Pattern::new(bytes, masks, 0)
+    }
+
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    pub fn capture_offset(&self) -> usize {
+        self.capture
+    }
+
+    pub fn matches_at(&self, haystack: &[u8], position: usize) -> bool {
+        match haystack.get(position..position + self.len()) {
+            Some(window) => window.iter()
+                .zip(self.bytes.iter().zip(&self.masks))
+                .all(|(&b, (&p, &m))| b & m == p),
+            None => false,
+        }
+    }
+
+    /// Returns the first match in `haystack`.
+    pub fn find(&self, haystack: &[u8]) -> Option<Match> {
+        self.find_iter(haystack).next()
+    }
+
+    /// Iterates over every match in `haystack`, including overlapping ones.
+    pub fn find_iter<'a>(&'a self, haystack: &'a [u8]) -> impl Iterator<Item = Match> + 'a {
+        let mut position = 0;
+        std::iter::from_fn(move || {
+            while position + self.len() <= haystack.len() {
+                if let Some((index, byte)) = self.anchor {
+                    let window = &haystack[position + index..=haystack.len() - self.len() + index];
+                    position += window.iter().position(|&b| b == byte)?;
+                }
+                let found = position;
+                position += 1;
+                if self.matches_at(haystack, found) {
+                    return Some(Match { offset: found as u64, capture: (found + self.capture) as u64 });
+                }
+            }
+            None
+        })
+    }
+
+    /// Scans a whole `Read + Seek` source in chunks, without moving its cursor.
+    pub fn scan<'a, R: BinaryPeeker + ?Sized>(&'a self, reader: &'a mut R) -> AobScanner<'a, R> {
+        AobScanner { pattern: self, reader, chunk_size: CHUNK_SIZE, ranges: None, found: VecDeque::new() }
+    }
+
+    /// Scans only `ranges` of a source, such as the selected regions of a process. A read error ends
+    /// the range it happened in, and scanning carries on with the next one.
+    pub fn scan_ranges<'a, R, I>(&'a self, reader: &'a mut R, ranges: I) -> AobScanner<'a, R>
+    where
+        R: BinaryPeeker + ?Sized,
+        I: IntoIterator<Item = Range<u64>>,
+    {
+        let ranges = Some(ranges.into_iter().collect());
+        AobScanner { pattern: self, reader, chunk_size: CHUNK_SIZE, ranges, found: VecDeque::new() }
+    }
+}
+
+impl FromStr for Pattern {
+    type Err = InterpreterError;
+
+    fn from_str(pattern: &str) -> Result<Pattern, InterpreterError> {
+        let mut bytes = Vec::new();
+        let mut masks = Vec::new();
+        let mut capture = None;
+        for token in pattern.split_whitespace() {
+            let token = match token.strip_prefix('|') {
+                Some(rest) => {
+                    if capture.replace(bytes.len()).is_some() {
+                        return Err(InterpreterError::BadPattern("more than one capture marker".to_string()));
+                    }
+                    if rest.is_empty() {
+                        continue;
+                    }
+                    rest
+                }
+                None => token,
+            };
+            let chars: Vec<char> = token.chars().collect();
+            let (high, low) = match chars[..] {
+                ['?'] => ('?', '?'),
+                [high, low] => (high, low),
+                _ => return Err(InterpreterError::BadPattern(format!("invalid token `{}`", token))),
+            };
+            let (high, high_mask) = parse_nibble(high, token)?;
+            let (low, low_mask) = parse_nibble(low, token)?;
+            bytes.push(high << 4 | low);
+            masks.push(high_mask << 4 | low_mask);
+        }
+        Pattern::new(bytes, masks, capture.unwrap_or(0))
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (&byte, &mask)) in self.bytes.iter().zip(&self.masks).enumerate() {
+            if i != 0 {
+                f.write_str(" ")?;
+            }
+            if i == self.capture && self.capture != 0 {
+                f.write_str("|")?;
+            }
+            let nibble = |value: u8, mask: u8| if mask == 0 { '?' } else { char::from_digit(value as u32, 16).unwrap().to_ascii_uppercase() };
+            write!(f, "{}{}", nibble(byte >> 4, mask >> 4), nibble(byte & 0xF, mask & 0xF))?;
+        }
+        if self.capture == self.bytes.len() {
+            f.write_str(" |")?;
+        }
+        Ok(())
+    }
+}
+
+/// Iterates over every match of a pattern in a `Read + Seek` source. Returned by `Pattern::scan`.
+///
+/// The source is read in chunks that overlap by the pattern length, so matches spanning a chunk
+/// boundary are found exactly once.
+pub struct AobScanner<'a, R: ?Sized> {
+    pattern: &'a Pattern,
+    reader: &'a mut R,
+    chunk_size: u64,
+    /// What is left to scan, or `None` for the whole stream until its length is known.
+    ranges: Option<VecDeque<Range<u64>>>,
+    found: VecDeque<Match>,
+}
+
+impl<R: BinaryPeeker + ?Sized> AobScanner<'_, R> {
+    /// Sets how many bytes are read per chunk. Defaults to 1 MiB.
+    pub fn with_chunk_size(mut self, chunk_size: u64) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+
+    fn scan_chunk(&mut self) -> Result<bool, InterpreterError> {
+        if self.ranges.is_none() {
+            let ranges = self.ranges.insert(VecDeque::new());
+            ranges.push_back(0..self.reader.total_len()?);
+        }
+        let ranges = self.ranges.as_mut().unwrap();
+        let Some(range) = ranges.front().cloned() else { return Ok(false) };
+        if range.start.saturating_add(self.pattern.len() as u64) > range.end {
+            ranges.pop_front();
+            return Ok(true);
+        }
+        let size = (self.chunk_size + self.pattern.len() as u64 - 1).min(range.end - range.start);
+        ranges[0].start = range.start.saturating_add(self.chunk_size);
+        let chunk = self.reader.peek_bytes(range.start, size as usize)?;
+        self.found.extend(self.pattern.find_iter(&chunk)
+            .filter(|m| m.offset < self.chunk_size)
+            .map(|m| Match { offset: range.start + m.offset, capture: range.start + m.capture }));
+        Ok(true)
+    }
+}
+
+impl<R: BinaryPeeker + ?Sized> Iterator for AobScanner<'_, R> {
+    type Item = Result<Match, InterpreterError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.found.is_empty() {
+            match self.scan_chunk() {
+                Ok(true) => {}
+                Ok(false) => return None,
+                Err(e) => {
+                    if let Some(ranges) = self.ranges.as_mut() {
+                        ranges.pop_front();
+                    }
+                    return Some(Err(e));
+                }
+            }
+        }
+        self.found.pop_front().map(Ok)
+    }
+}
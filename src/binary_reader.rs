@@ -1,8 +1,141 @@
-use std::io::{Read, Seek, SeekFrom};
-use byteorder::{ReadBytesExt, NativeEndian, ByteOrder};
-use crate::{peek_type};
+use std::io::{Read, Seek, SeekFrom, Write};
+use byteorder::{ReadBytesExt, WriteBytesExt, NativeEndian, ByteOrder};
+use crate::{peek_type, peek_type_endian, read_type_endian};
+use crate::address::{Address, AddressMap, PointerExpr, PointerWidth};
+use crate::encoding::Encoding;
+use crate::endian::Endian;
+use crate::error::InterpreterError;
+use crate::pod::{EndianPod, as_bytes_mut, needs_swap};
+use crate::record_iter::{RecordIter, SentinelIter, StridedIter};
 use paste::paste;
 
+/// Upper bound for buffers whose size comes from a value read out of the stream.
+pub const MAX_ALLOCATION: u64 = 256 * 1024 * 1024;
+
+pub(crate) fn check_allocation(size: u64) -> Result<usize, InterpreterError> {
+    if size > MAX_ALLOCATION {
+        return Err(InterpreterError::AllocationTooLarge { size, limit: MAX_ALLOCATION });
+    }
+    Ok(size as usize)
+}
+
+/// Buffers sized by a value read out of the stream start this small and grow as the data arrives, so a
+/// corrupt length fails at the end of the stream instead of allocating up front.
+const INITIAL_CAPACITY: usize = 64 * 1024;
+
+fn read_bytes_growing<R: Read + ?Sized>(reader: &mut R, size: usize) -> std::io::Result<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(size.min(INITIAL_CAPACITY));
+    reader.take(size as u64).read_to_end(&mut bytes)?;
+    if bytes.len() < size {
+        return Err(std::io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(bytes)
+}
+
+/// How `expect_fill` and friends react to padding that doesn't hold the expected byte.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum PaddingMode {
+    /// Return `InterpreterError::BadPadding`.
+    #[default]
+    Strict,
+    /// Log a warning and carry on.
+    Permissive,
+}
+
+/// Integer types usable as the length prefix of a Pascal string.
+pub trait LengthPrefix {
+    const MAX: u64;
+    fn read_len<E: ByteOrder, R: Read + ?Sized>(reader: &mut R) -> std::io::Result<u64>;
+    fn write_len<E: ByteOrder, W: Write + ?Sized>(writer: &mut W, len: u64) -> std::io::Result<()>;
+}
+
+impl LengthPrefix for u8 {
+    const MAX: u64 = u8::MAX as u64;
+    fn read_len<E: ByteOrder, R: Read + ?Sized>(reader: &mut R) -> std::io::Result<u64> {
+        Ok(reader.read_u8()? as u64)
+    }
+    fn write_len<E: ByteOrder, W: Write + ?Sized>(writer: &mut W, len: u64) -> std::io::Result<()> {
+        writer.write_u8(len as u8)
+    }
+}
+
+macro_rules! length_prefix {
+    ($ty:ty) => {
+        paste! {
+            impl LengthPrefix for $ty {
+                const MAX: u64 = $ty::MAX as u64;
+                fn read_len<E: ByteOrder, R: Read + ?Sized>(reader: &mut R) -> std::io::Result<u64> {
+                    Ok(reader.[<read_ $ty>]::<E>()? as u64)
+                }
+                fn write_len<E: ByteOrder, W: Write + ?Sized>(writer: &mut W, len: u64) -> std::io::Result<()> {
+                    writer.[<write_ $ty>]::<E>(len as $ty)
+                }
+            }
+        }
+    };
+}
+
+length_prefix!(u16);
+length_prefix!(u32);
+length_prefix!(u64);
+
+fn read_uleb<R: Read + ?Sized>(reader: &mut R, bits: u32) -> Result<u128, InterpreterError> {
+    let max_bytes = bits.div_ceil(7);
+    let mut result = 0u128;
+    for i in 0..max_bytes {
+        let byte = reader.read_u8()?;
+        let shift = i * 7;
+        let payload = (byte & 0x7F) as u128;
+        if i == max_bytes - 1 {
+            if byte & 0x80 != 0 {
+                return Err(InterpreterError::VarintOverlong);
+            }
+            if payload >> (bits - shift) != 0 {
+                return Err(InterpreterError::VarintOverflow(bits));
+            }
+        }
+        result |= payload << shift;
+        if byte & 0x80 == 0 {
+            break;
+        }
+    }
+    Ok(result)
+}
+
+fn read_sleb<R: Read + ?Sized>(reader: &mut R, bits: u32) -> Result<i128, InterpreterError> {
+    let max_bytes = bits.div_ceil(7);
+    let mut result = 0u128;
+    for i in 0..max_bytes {
+        let byte = reader.read_u8()?;
+        let shift = i * 7;
+        let payload = (byte & 0x7F) as u128;
+        if i == max_bytes - 1 {
+            if byte & 0x80 != 0 {
+                return Err(InterpreterError::VarintOverlong);
+            }
+            // The bits past the width of the type must all repeat the sign bit.
+            let sign_and_above = payload >> (bits - shift - 1);
+            if sign_and_above != 0 && sign_and_above != 0x7F >> (bits - shift - 1) {
+                return Err(InterpreterError::VarintOverflow(bits));
+            }
+        }
+        result |= payload << shift;
+        if byte & 0x80 == 0 {
+            if shift + 7 < 128 && byte & 0x40 != 0 {
+                result |= !0u128 << (shift + 7);
+            }
+            break;
+        }
+    }
+    Ok(result as i128)
+}
+
+/// Number of bytes `value` takes as a prefix varint.
+pub(crate) fn prefix_varint_len(value: u64) -> usize {
+    let bits = 64 - value.leading_zeros().min(63);
+    if bits > 56 { 9 } else { (bits as usize - 1) / 7 + 1 }
+}
+
 pub trait BinaryReader: ReadBytesExt {
 
     fn read_bytes(&mut self, size: usize) -> std::io::Result<Vec<u8>> {
@@ -13,7 +146,8 @@ pub trait BinaryReader: ReadBytesExt {
 
     fn read_cstr(&mut self) -> std::io::Result<String> {
         let mut chrs = Vec::new();
-        while let chr = self.read_u8()? {
+        loop {
+            let chr = self.read_u8()?;
             if chr == 0 {
                 break;
             }
@@ -22,9 +156,12 @@ pub trait BinaryReader: ReadBytesExt {
         Ok(String::from_utf8(chrs).unwrap())
     }
 
+    /// Reads native endian UTF-16 code units. Use `read_cstr_encoded` with `Encoding::Utf16LE` or `Encoding::Utf16BE`
+    /// for a fixed byte order.
     fn read_wcstr(&mut self) -> std::io::Result<String> {
         let mut chrs = Vec::new();
-        while let chr = self.read_u16::<NativeEndian>()? {
+        loop {
+            let chr = self.read_u16::<NativeEndian>()?;
             if chr == 0 {
                 break;
             }
@@ -35,10 +172,13 @@ pub trait BinaryReader: ReadBytesExt {
 
     fn read_fixed_cstr(&mut self, size: usize) -> std::io::Result<String> {
         let mut chrs = vec![0u8; size];
-        self.read(&mut chrs[..])?;
+        // A short read leaves the rest of the field zeroed.
+        let _ = self.read(&mut chrs[..])?;
         Ok(String::from_utf8(chrs).unwrap())
     }
 
+    /// Reads native endian UTF-16 code units. Use `read_fixed_cstr_encoded` with `Encoding::Utf16LE` or `Encoding::Utf16BE`
+    /// for a fixed byte order.
     fn read_fixed_wcstr(&mut self, size: usize) -> std::io::Result<String> {
         let mut chrs = Vec::with_capacity(size);
         for _ in 0..size {
@@ -46,18 +186,260 @@ pub trait BinaryReader: ReadBytesExt {
         }
         Ok(String::from_utf16(chrs.as_slice()).unwrap())
     }
+
+    /// Reads and discards `count` bytes.
+    fn skip(&mut self, count: u64) -> std::io::Result<()> {
+        let mut buf = [0u8; 4096];
+        let mut remaining = count;
+        while remaining > 0 {
+            let len = remaining.min(buf.len() as u64) as usize;
+            self.read_exact(&mut buf[..len])?;
+            remaining -= len as u64;
+        }
+        Ok(())
+    }
+
+    /// Fills `dst` with values stored in `E` order using a single read followed by an in place byte swap.
+    fn read_into<T: EndianPod, E: ByteOrder>(&mut self, dst: &mut [T]) -> std::io::Result<()> {
+        self.read_exact(as_bytes_mut(dst))?;
+        if needs_swap::<E>() {
+            for value in dst.iter_mut() {
+                value.swap_bytes();
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads `count` values stored in `E` order. See `read_into`. The buffer grows as values are read,
+    /// so a corrupt count fails at the end of the stream rather than allocating `count` values first.
+    fn read_array<T: EndianPod, E: ByteOrder>(&mut self, count: usize) -> Result<Vec<T>, InterpreterError> {
+        check_allocation((count as u64).saturating_mul(std::mem::size_of::<T>() as u64))?;
+        let chunk = match std::mem::size_of::<T>() {
+            0 => count.max(1),
+            size => (INITIAL_CAPACITY / size).max(1),
+        };
+        let mut values: Vec<T> = Vec::with_capacity(count.min(chunk));
+        while values.len() < count {
+            let start = values.len();
+            // Pod types are valid for any bit pattern, including all zeroes.
+            values.resize(start + (count - start).min(chunk), unsafe { std::mem::zeroed() });
+            self.read_into::<T, E>(&mut values[start..])?;
+        }
+        Ok(values)
+    }
+
+    /// Iterates over `count` consecutive records stored in `E` order.
+    fn read_iter<T: EndianPod, E: ByteOrder>(&mut self, count: usize) -> RecordIter<'_, Self, T, E> {
+        RecordIter::new(self, count)
+    }
+
+    /// Iterates over records stored in `E` order until `is_sentinel` matches one. The sentinel is consumed.
+    fn read_until_sentinel<T: EndianPod, E: ByteOrder, F: FnMut(&T) -> bool>(&mut self, is_sentinel: F) -> SentinelIter<'_, Self, T, E, F> {
+        SentinelIter::new(self, is_sentinel)
+    }
+
+    /// Reads a NUL terminated string in `encoding`. The terminator is `encoding.unit_size()` bytes wide.
+    fn read_cstr_encoded(&mut self, encoding: Encoding) -> Result<String, InterpreterError> {
+        let mut bytes = Vec::new();
+        let mut unit = vec![0u8; encoding.unit_size()];
+        loop {
+            self.read_exact(&mut unit)?;
+            if unit.iter().all(|&b| b == 0) {
+                break;
+            }
+            bytes.extend_from_slice(&unit);
+        }
+        encoding.decode(&bytes)
+    }
+
+    /// Reads a fixed size string of `size` code units in `encoding`, stopping at the first terminator.
+    fn read_fixed_cstr_encoded(&mut self, size: usize, encoding: Encoding) -> Result<String, InterpreterError> {
+        let unit_size = encoding.unit_size();
+        let byte_len = size.checked_mul(unit_size)
+            .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "string field size overflows usize"))?;
+        let bytes = read_bytes_growing(self, byte_len)?;
+        let len = bytes.chunks_exact(unit_size)
+            .position(|unit| unit.iter().all(|&b| b == 0))
+            .unwrap_or(size);
+        encoding.decode(&bytes[..len * unit_size])
+    }
+
+    /// Reads a string preceded by a `P` length prefix counting code units in `encoding`.
+    fn read_pstr<P: LengthPrefix, E: ByteOrder>(&mut self, encoding: Encoding) -> Result<String, InterpreterError> {
+        let len = P::read_len::<E, _>(self)?;
+        let size = check_allocation(len.saturating_mul(encoding.unit_size() as u64))?;
+        encoding.decode(&read_bytes_growing(self, size)?)
+    }
+
+    /// Reads an `i32` in the 7-bit encoded form used by .NET's `BinaryWriter.Write7BitEncodedInt`.
+    fn read_7bit_encoded_i32(&mut self) -> Result<i32, InterpreterError> {
+        let mut value = 0u32;
+        for i in 0..5 {
+            let byte = self.read_u8()?;
+            if i == 4 && byte > 0x0F {
+                return Err(InterpreterError::VarintOverflow(32));
+            }
+            value |= ((byte & 0x7F) as u32) << (i * 7);
+            if byte & 0x80 == 0 {
+                return Ok(value as i32);
+            }
+        }
+        Err(InterpreterError::VarintOverflow(32))
+    }
+
+    /// Reads an unsigned LEB128 value. Padding up to the maximum width is accepted, anything longer is not.
+    fn read_uleb128(&mut self) -> Result<u64, InterpreterError> {
+        Ok(read_uleb(self, 64)? as u64)
+    }
+
+    fn read_uleb128_u128(&mut self) -> Result<u128, InterpreterError> {
+        read_uleb(self, 128)
+    }
+
+    /// Reads a signed LEB128 value. Padding up to the maximum width is accepted, anything longer is not.
+    fn read_sleb128(&mut self) -> Result<i64, InterpreterError> {
+        Ok(read_sleb(self, 64)? as i64)
+    }
+
+    fn read_sleb128_i128(&mut self) -> Result<i128, InterpreterError> {
+        read_sleb(self, 128)
+    }
+
+    /// Reads a protobuf style `sint64`: a ULEB128 holding the zigzag encoded value.
+    fn read_varint_zigzag(&mut self) -> Result<i64, InterpreterError> {
+        let value = self.read_uleb128()?;
+        Ok((value >> 1) as i64 ^ -((value & 1) as i64))
+    }
+
+    /// Reads a little endian prefix varint, where the number of trailing zero bits in the first byte
+    /// gives the number of bytes that follow. A first byte of zero is followed by a full `u64`.
+    /// Non-canonical encodings are rejected.
+    fn read_prefix_varint(&mut self) -> Result<u64, InterpreterError> {
+        let first = self.read_u8()?;
+        let len = first.trailing_zeros() as usize + 1;
+        let value = if len == 9 {
+            self.read_u64::<byteorder::LittleEndian>()?
+        } else {
+            let mut buf = [0u8; 8];
+            buf[0] = first;
+            self.read_exact(&mut buf[1..len])?;
+            u64::from_le_bytes(buf) >> len
+        };
+        if prefix_varint_len(value) != len {
+            return Err(InterpreterError::VarintOverlong);
+        }
+        Ok(value)
+    }
+
+    /// Reads a UTF-8 string written by .NET's `BinaryWriter.Write(string)`.
+    fn read_dotnet_string(&mut self) -> Result<String, InterpreterError> {
+        let len = self.read_7bit_encoded_i32()?;
+        if len < 0 {
+            return Err(InterpreterError::DecodeError("7-bit encoded string length"));
+        }
+        let size = check_allocation(len as u64)?;
+        Encoding::Utf8.decode(&read_bytes_growing(self, size)?)
+    }
+
+    read_type_endian!(u16);
+    read_type_endian!(i16);
+    read_type_endian!(u32);
+    read_type_endian!(i32);
+    read_type_endian!(u64);
+    read_type_endian!(i64);
+
+    /// Reads a pointer of `width` bytes, for formats whose pointer size and byte order are only known at runtime.
+    fn read_pointer_endian(&mut self, width: PointerWidth, endian: Endian) -> std::io::Result<u64> {
+        match width {
+            PointerWidth::U32 => self.read_u32_endian(endian).map(u64::from),
+            PointerWidth::U64 => self.read_u64_endian(endian),
+        }
+    }
 }
 
 impl<R: ReadBytesExt + ?Sized> BinaryReader for R {}
 
 pub trait BinaryPeeker: ReadBytesExt + Seek {
 
+    /// Length of the stream in bytes. The cursor is left where it was.
+    ///
+    /// Not cached: the trait is implemented for every `Read + Seek` and has nowhere to keep it, and a file
+    /// being written or a process's memory can change length. Callers that ask repeatedly keep it themselves.
+    fn total_len(&mut self) -> std::io::Result<u64> {
+        let start = self.stream_position()?;
+        let len = self.seek(SeekFrom::End(0))?;
+        if len != start {
+            self.seek(SeekFrom::Start(start))?;
+        }
+        Ok(len)
+    }
+
+    /// Number of bytes between the cursor and the end of the stream.
+    fn remaining(&mut self) -> std::io::Result<u64> {
+        let position = self.stream_position()?;
+        Ok(self.total_len()?.saturating_sub(position))
+    }
+
+    fn is_eof(&mut self) -> std::io::Result<bool> {
+        Ok(self.remaining()? == 0)
+    }
+
+    /// Errors with `Truncated` unless at least `size` bytes remain.
+    fn ensure_remaining(&mut self, size: u64) -> Result<(), InterpreterError> {
+        let remaining = self.remaining()?;
+        if size > remaining {
+            return Err(InterpreterError::Truncated { offset: self.stream_position()?, needed: size, remaining });
+        }
+        Ok(())
+    }
+
+    /// Reads everything from the cursor to the end of the stream.
+    fn read_to_end_bytes(&mut self) -> Result<Vec<u8>, InterpreterError> {
+        let size = check_allocation(self.remaining()?)?;
+        Ok(self.read_bytes(size)?)
+    }
+
+    /// Like `BinaryReader::read_pstr`, but fails with `Truncated` before allocating if the stream is too short.
+    fn read_pstr_checked<P: LengthPrefix, E: ByteOrder>(&mut self, encoding: Encoding) -> Result<String, InterpreterError> {
+        let len = P::read_len::<E, _>(self)?;
+        let size = len.saturating_mul(encoding.unit_size() as u64);
+        self.ensure_remaining(size)?;
+        let size = check_allocation(size)?;
+        encoding.decode(&self.read_bytes(size)?)
+    }
+
+    /// Like `BinaryReader::read_dotnet_string`, but fails with `Truncated` before allocating if the stream is too short.
+    fn read_dotnet_string_checked(&mut self) -> Result<String, InterpreterError> {
+        let len = self.read_7bit_encoded_i32()?;
+        if len < 0 {
+            return Err(InterpreterError::DecodeError("7-bit encoded string length"));
+        }
+        self.ensure_remaining(len as u64)?;
+        let size = check_allocation(len as u64)?;
+        Encoding::Utf8.decode(&self.read_bytes(size)?)
+    }
+
+    /// Like `BinaryReader::read_array`, but fails with `Truncated` before allocating if the stream is too short.
+    fn read_array_checked<T: EndianPod, E: ByteOrder>(&mut self, count: usize) -> Result<Vec<T>, InterpreterError> {
+        self.ensure_remaining((count as u64).saturating_mul(std::mem::size_of::<T>() as u64))?;
+        BinaryReader::read_array::<T, E>(self, count)
+    }
+
     fn peek_bytes(&mut self, position: u64, size: usize) -> std::io::Result<Vec<u8>> {
         let start = self.stream_position()?;
         self.seek(SeekFrom::Start(position))?;
         let bytes = self.read_bytes(size);
         self.seek(SeekFrom::Start(start))?;
-        return bytes;
+        bytes
+    }
+
+    /// Reads everything from `position` to the end of the stream then returns to original position
+    fn peek_rest(&mut self, position: u64) -> Result<Vec<u8>, InterpreterError> {
+        let start = self.stream_position()?;
+        self.seek(SeekFrom::Start(position))?;
+        let bytes = self.read_to_end_bytes();
+        self.seek(SeekFrom::Start(start))?;
+        bytes
     }
 
     fn peek_cstr(&mut self, position: u64) -> std::io::Result<String> {
@@ -65,15 +447,17 @@ pub trait BinaryPeeker: ReadBytesExt + Seek {
         self.seek(SeekFrom::Start(position))?;
         let cstr = self.read_cstr();
         self.seek(SeekFrom::Start(start))?;
-        return cstr;
+        cstr
     }
 
+    /// Reads native endian UTF-16 code units. Use `peek_cstr_encoded` with `Encoding::Utf16LE` or `Encoding::Utf16BE`
+    /// for a fixed byte order.
     fn peek_wcstr(&mut self, position: u64) -> std::io::Result<String> {
         let start = self.stream_position()?;
         self.seek(SeekFrom::Start(position))?;
         let wcstr = self.read_wcstr();
         self.seek(SeekFrom::Start(start))?;
-        return wcstr;
+        wcstr
     }
 
     fn peek_fixed_cstr(&mut self, position: u64, size: usize) -> std::io::Result<String> {
@@ -81,15 +465,193 @@ pub trait BinaryPeeker: ReadBytesExt + Seek {
         self.seek(SeekFrom::Start(position))?;
         let cstr = self.read_fixed_cstr(size);
         self.seek(SeekFrom::Start(start))?;
-        return cstr;
+        cstr
     }
 
+    /// Reads native endian UTF-16 code units. Use `peek_fixed_cstr_encoded` with `Encoding::Utf16LE` or `Encoding::Utf16BE`
+    /// for a fixed byte order.
     fn peek_fixed_wcstr(&mut self, position: u64, size: usize) -> std::io::Result<String> {
         let start = self.stream_position()?;
         self.seek(SeekFrom::Start(position))?;
         let wcstr = self.read_fixed_wcstr(size);
         self.seek(SeekFrom::Start(start))?;
-        return wcstr;
+        wcstr
+    }
+
+    fn peek_cstr_encoded(&mut self, position: u64, encoding: Encoding) -> Result<String, InterpreterError> {
+        let start = self.stream_position()?;
+        self.seek(SeekFrom::Start(position))?;
+        let cstr = self.read_cstr_encoded(encoding);
+        self.seek(SeekFrom::Start(start))?;
+        cstr
+    }
+
+    fn peek_fixed_cstr_encoded(&mut self, position: u64, size: usize, encoding: Encoding) -> Result<String, InterpreterError> {
+        let start = self.stream_position()?;
+        self.seek(SeekFrom::Start(position))?;
+        let cstr = self.read_fixed_cstr_encoded(size, encoding);
+        self.seek(SeekFrom::Start(start))?;
+        cstr
+    }
+
+    fn peek_pstr<P: LengthPrefix, E: ByteOrder>(&mut self, position: u64, encoding: Encoding) -> Result<String, InterpreterError> {
+        let start = self.stream_position()?;
+        self.seek(SeekFrom::Start(position))?;
+        let pstr = self.read_pstr_checked::<P, E>(encoding);
+        self.seek(SeekFrom::Start(start))?;
+        pstr
+    }
+
+    fn peek_dotnet_string(&mut self, position: u64) -> Result<String, InterpreterError> {
+        let start = self.stream_position()?;
+        self.seek(SeekFrom::Start(position))?;
+        let string = self.read_dotnet_string_checked();
+        self.seek(SeekFrom::Start(start))?;
+        string
+    }
+
+    fn peek_into<T: EndianPod, E: ByteOrder>(&mut self, position: u64, dst: &mut [T]) -> std::io::Result<()> {
+        let start = self.stream_position()?;
+        self.seek(SeekFrom::Start(position))?;
+        let result = self.read_into::<T, E>(dst);
+        self.seek(SeekFrom::Start(start))?;
+        result
+    }
+
+    fn peek_array<T: EndianPod, E: ByteOrder>(&mut self, position: u64, count: usize) -> Result<Vec<T>, InterpreterError> {
+        let start = self.stream_position()?;
+        self.seek(SeekFrom::Start(position))?;
+        let array = self.read_array_checked::<T, E>(count);
+        self.seek(SeekFrom::Start(start))?;
+        array
+    }
+
+    /// Iterates over `count` records placed `stride` bytes apart starting at `offset`, without moving the cursor.
+    fn strided_iter<T: EndianPod, E: ByteOrder>(&mut self, offset: u64, stride: u64, count: usize) -> StridedIter<'_, Self, T, E> {
+        StridedIter::new(self, offset, stride, count)
+    }
+
+    fn peek_uleb128(&mut self, position: u64) -> Result<u64, InterpreterError> {
+        let start = self.stream_position()?;
+        self.seek(SeekFrom::Start(position))?;
+        let value = self.read_uleb128();
+        self.seek(SeekFrom::Start(start))?;
+        value
+    }
+
+    fn peek_sleb128(&mut self, position: u64) -> Result<i64, InterpreterError> {
+        let start = self.stream_position()?;
+        self.seek(SeekFrom::Start(position))?;
+        let value = self.read_sleb128();
+        self.seek(SeekFrom::Start(start))?;
+        value
+    }
+
+    fn peek_varint_zigzag(&mut self, position: u64) -> Result<i64, InterpreterError> {
+        let start = self.stream_position()?;
+        self.seek(SeekFrom::Start(position))?;
+        let value = self.read_varint_zigzag();
+        self.seek(SeekFrom::Start(start))?;
+        value
+    }
+
+    fn peek_prefix_varint(&mut self, position: u64) -> Result<u64, InterpreterError> {
+        let start = self.stream_position()?;
+        self.seek(SeekFrom::Start(position))?;
+        let value = self.read_prefix_varint();
+        self.seek(SeekFrom::Start(start))?;
+        value
+    }
+
+    /// Seeks forward to the next multiple of `alignment` and returns the new position.
+    fn align_to(&mut self, alignment: u64) -> std::io::Result<u64> {
+        let position = self.stream_position()?;
+        if alignment <= 1 {
+            return Ok(position);
+        }
+        self.seek(SeekFrom::Start(position.next_multiple_of(alignment)))
+    }
+
+    /// Like `align_to`, but checks that the skipped padding is zero.
+    fn align_to_zeros(&mut self, alignment: u64, mode: PaddingMode) -> Result<u64, InterpreterError> {
+        let position = self.stream_position()?;
+        if alignment <= 1 {
+            return Ok(position);
+        }
+        let aligned = position.next_multiple_of(alignment);
+        self.expect_zeros((aligned - position) as usize, mode)?;
+        Ok(aligned)
+    }
+
+    /// Reads `count` bytes and checks that every one is `fill`.
+    fn expect_fill(&mut self, count: usize, fill: u8, mode: PaddingMode) -> Result<(), InterpreterError> {
+        let offset = self.stream_position()?;
+        let found = self.read_bytes(count)?;
+        if let Some(i) = found.iter().position(|&b| b != fill) {
+            let offset = offset + i as u64;
+            match mode {
+                PaddingMode::Strict => {
+                    return Err(InterpreterError::BadPadding { offset, expected: fill, found: found[i] });
+                }
+                PaddingMode::Permissive => {
+                    log::warn!("unexpected padding at {:#x}: expected {:#04x}, found {:02X?}", offset, fill, &found[i..]);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn expect_zeros(&mut self, count: usize, mode: PaddingMode) -> Result<(), InterpreterError> {
+        self.expect_fill(count, 0, mode)
+    }
+
+    /// Reads `magic.len()` bytes and errors with `BadMagic` if they differ from `magic`.
+    fn expect_magic(&mut self, magic: &[u8]) -> Result<(), InterpreterError> {
+        let offset = self.stream_position()?;
+        let found = self.read_bytes(magic.len())?;
+        if found != magic {
+            return Err(InterpreterError::BadMagic { expected: magic.to_vec(), found, offset });
+        }
+        Ok(())
+    }
+
+    /// Like `expect_magic`, but also accepts the reversed bytes. `magic` is given in little endian order,
+    /// e.g. `&[0xFF, 0xFE]` for a UTF-16 BOM, and the matching byte order is returned.
+    fn expect_magic_endian(&mut self, magic: &[u8]) -> Result<Endian, InterpreterError> {
+        let offset = self.stream_position()?;
+        let found = self.read_bytes(magic.len())?;
+        if found == magic {
+            return Ok(Endian::Little);
+        }
+        if found.iter().eq(magic.iter().rev()) {
+            return Ok(Endian::Big);
+        }
+        Err(InterpreterError::BadMagic { expected: magic.to_vec(), found, offset })
+    }
+
+    fn peek_magic(&mut self, position: u64, magic: &[u8]) -> Result<(), InterpreterError> {
+        let start = self.stream_position()?;
+        self.seek(SeekFrom::Start(position))?;
+        let result = self.expect_magic(magic);
+        self.seek(SeekFrom::Start(start))?;
+        result
+    }
+
+    fn peek_magic_endian(&mut self, position: u64, magic: &[u8]) -> Result<Endian, InterpreterError> {
+        let start = self.stream_position()?;
+        self.seek(SeekFrom::Start(position))?;
+        let result = self.expect_magic_endian(magic);
+        self.seek(SeekFrom::Start(start))?;
+        result
+    }
+
+    /// Returns true if the bytes at `position` are `magic`. Running past the end of the stream is not a match.
+    fn has_magic(&mut self, position: u64, magic: &[u8]) -> std::io::Result<bool> {
+        match self.peek_bytes(position, magic.len()) {
+            Ok(found) => Ok(found == magic),
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Ok(false),
+            Err(e) => Err(e),
+        }
     }
 
     fn peek_u8(&mut self, position: u64) -> std::io::Result<u8> {
@@ -97,7 +659,7 @@ pub trait BinaryPeeker: ReadBytesExt + Seek {
         self.seek(SeekFrom::Start(position))?;
         let byte = self.read_u8();
         self.seek(SeekFrom::Start(start))?;
-        return byte;
+        byte
     }
 
     fn peek_i8(&mut self, position: u64) -> std::io::Result<i8> {
@@ -105,7 +667,7 @@ pub trait BinaryPeeker: ReadBytesExt + Seek {
         self.seek(SeekFrom::Start(position))?;
         let byte = self.read_i8();
         self.seek(SeekFrom::Start(start))?;
-        return byte;
+        byte
     }
     peek_type!(u16);
     peek_type!(i16);
@@ -115,6 +677,68 @@ pub trait BinaryPeeker: ReadBytesExt + Seek {
     peek_type!(i64);
     peek_type!(u128);
     peek_type!(i128);
+
+    /// Peeks a pointer of `width` bytes stored in `E` order.
+    fn peek_pointer<E: ByteOrder>(&mut self, position: u64, width: PointerWidth) -> std::io::Result<u64> {
+        match width {
+            PointerWidth::U32 => self.peek_u32::<E>(position).map(u64::from),
+            PointerWidth::U64 => self.peek_u64::<E>(position),
+        }
+    }
+
+    peek_type_endian!(u16);
+    peek_type_endian!(i16);
+    peek_type_endian!(u32);
+    peek_type_endian!(i32);
+    peek_type_endian!(u64);
+    peek_type_endian!(i64);
+
+    /// Peeks a pointer of `width` bytes in a byte order chosen at runtime.
+    fn peek_pointer_endian(&mut self, position: u64, width: PointerWidth, endian: Endian) -> std::io::Result<u64> {
+        match width {
+            PointerWidth::U32 => self.peek_u32_endian(position, endian).map(u64::from),
+            PointerWidth::U64 => self.peek_u64_endian(position, endian),
+        }
+    }
+
+    /// Resolves an x86 instruction at `position` that ends in a 32-bit displacement relative to the next
+    /// instruction, such as `mov rax, [rip+disp32]` (`insn_len` 7) or `call rel32` (`insn_len` 5).
+    /// `position` is a stream offset and `map` places it in memory.
+    fn resolve_rel32<M: AddressMap + ?Sized>(&mut self, position: u64, insn_len: u64, map: &M) -> Result<Address, InterpreterError> {
+        if insn_len < 4 {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "instruction too short for a 32-bit displacement").into());
+        }
+        let overflow = || InterpreterError::Malformed { what: "rel32 instruction", offset: position };
+        let next = position.checked_add(insn_len).ok_or_else(overflow)?;
+        let disp = self.peek_i32::<byteorder::LittleEndian>(next - 4)?;
+        let va = map.to_va(position).ok_or(InterpreterError::UnmappedAddress(position))?;
+        let next_va = va.checked_add(insn_len).ok_or_else(overflow)?;
+        Ok(Address::from_va(next_va.wrapping_add_signed(disp as i64), map))
+    }
+
+    /// Resolves the target of the `call rel32` (`E8`) or `jmp rel32` (`E9`) at `position`.
+    fn follow_call<M: AddressMap + ?Sized>(&mut self, position: u64, map: &M) -> Result<Address, InterpreterError> {
+        match self.peek_u8(position)? {
+            0xE8 | 0xE9 => self.resolve_rel32(position, 5, map),
+            opcode => Err(InterpreterError::UnexpectedOpcode { offset: position, opcode }),
+        }
+    }
+
+    /// Evaluates a pointer chain such as `[[base+0x10]+0x8]+0x40`, reading each pointer as `width` bytes
+    /// in `E` order. Symbols are looked up with `resolve`, and addresses are virtual addresses placed in
+    /// the stream by `map`.
+    fn resolve_pointer_path<E, M, S>(&mut self, path: &PointerExpr, width: PointerWidth, map: &M, resolve: &S) -> Result<Address, InterpreterError>
+    where
+        E: ByteOrder,
+        M: AddressMap + ?Sized,
+        S: Fn(&str) -> Option<u64> + ?Sized,
+    {
+        let va = path.eval(resolve, &mut |va| {
+            let offset = map.to_offset(va).ok_or(InterpreterError::UnmappedAddress(va))?;
+            Ok(self.peek_pointer::<E>(offset, width)?)
+        })?;
+        Ok(Address::from_va(va, map))
+    }
 }
 
 impl<R: ReadBytesExt + Seek> BinaryPeeker for R {}
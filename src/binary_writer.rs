@@ -0,0 +1,186 @@
+use std::io::{Seek, SeekFrom};
+use byteorder::{WriteBytesExt, NativeEndian, ByteOrder};
+use crate::{poke_type};
+use crate::binary_reader::{LengthPrefix, prefix_varint_len};
+use crate::encoding::Encoding;
+use crate::pod::{EndianPod, as_bytes, needs_swap};
+use crate::error::InterpreterError;
+use paste::paste;
+
+pub trait BinaryWriter: WriteBytesExt {
+
+    fn write_bytes(&mut self, bytes: &[u8]) -> std::io::Result<()> {
+        self.write_all(bytes)
+    }
+
+    /// Writes `values` in `E` order. The counterpart of `BinaryReader::read_array`.
+    fn write_array<T: EndianPod, E: ByteOrder>(&mut self, values: &[T]) -> std::io::Result<()> {
+        if !needs_swap::<E>() {
+            return self.write_all(as_bytes(values));
+        }
+        let mut swapped = values.to_vec();
+        for value in swapped.iter_mut() {
+            value.swap_bytes();
+        }
+        self.write_all(as_bytes(&swapped))
+    }
+
+    fn write_cstr(&mut self, string: &str) -> std::io::Result<()> {
+        self.write_all(string.as_bytes())?;
+        self.write_u8(0)
+    }
+
+    /// Writes native endian UTF-16 code units. Use `write_cstr_encoded` with `Encoding::Utf16LE` or
+    /// `Encoding::Utf16BE` for a fixed byte order.
+    fn write_wcstr(&mut self, string: &str) -> std::io::Result<()> {
+        for chr in string.encode_utf16() {
+            self.write_u16::<NativeEndian>(chr)?;
+        }
+        self.write_u16::<NativeEndian>(0)
+    }
+
+    /// Writes `string` in `encoding` followed by a terminator `encoding.unit_size()` bytes wide.
+    fn write_cstr_encoded(&mut self, string: &str, encoding: Encoding) -> Result<(), InterpreterError> {
+        self.write_all(&encoding.encode(string)?)?;
+        self.write_all(&vec![0u8; encoding.unit_size()])?;
+        Ok(())
+    }
+
+    /// Writes `string` in `encoding` into a field of `size` code units, padding with zeros.
+    /// A string that exactly fills the field is written without a terminator.
+    fn write_fixed_cstr_encoded(&mut self, string: &str, size: usize, encoding: Encoding) -> Result<(), InterpreterError> {
+        let mut bytes = encoding.encode(string)?;
+        let max = size.checked_mul(encoding.unit_size())
+            .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "string field size overflows usize"))?;
+        if bytes.len() > max {
+            return Err(InterpreterError::StringTooLong { len: bytes.len() / encoding.unit_size(), max: size });
+        }
+        bytes.resize(max, 0);
+        self.write_all(&bytes)?;
+        Ok(())
+    }
+
+    /// Writes `string` in `encoding` preceded by a `P` length prefix counting code units.
+    fn write_pstr<P: LengthPrefix, E: ByteOrder>(&mut self, string: &str, encoding: Encoding) -> Result<(), InterpreterError> {
+        let bytes = encoding.encode(string)?;
+        let len = bytes.len() / encoding.unit_size();
+        if len as u64 > P::MAX {
+            return Err(InterpreterError::StringTooLong { len, max: P::MAX as usize });
+        }
+        P::write_len::<E, _>(self, len as u64)?;
+        self.write_all(&bytes)?;
+        Ok(())
+    }
+
+    /// Writes an `i32` in the 7-bit encoded form used by .NET's `BinaryWriter.Write7BitEncodedInt`.
+    fn write_7bit_encoded_i32(&mut self, value: i32) -> std::io::Result<()> {
+        let mut value = value as u32;
+        while value >= 0x80 {
+            self.write_u8(value as u8 | 0x80)?;
+            value >>= 7;
+        }
+        self.write_u8(value as u8)
+    }
+
+    fn write_uleb128(&mut self, value: u64) -> std::io::Result<()> {
+        self.write_uleb128_u128(value as u128)
+    }
+
+    fn write_uleb128_u128(&mut self, mut value: u128) -> std::io::Result<()> {
+        while value >= 0x80 {
+            self.write_u8(value as u8 | 0x80)?;
+            value >>= 7;
+        }
+        self.write_u8(value as u8)
+    }
+
+    fn write_sleb128(&mut self, value: i64) -> std::io::Result<()> {
+        self.write_sleb128_i128(value as i128)
+    }
+
+    fn write_sleb128_i128(&mut self, mut value: i128) -> std::io::Result<()> {
+        loop {
+            let byte = (value & 0x7F) as u8;
+            value >>= 7;
+            if (value == 0 && byte & 0x40 == 0) || (value == -1 && byte & 0x40 != 0) {
+                return self.write_u8(byte);
+            }
+            self.write_u8(byte | 0x80)?;
+        }
+    }
+
+    fn write_varint_zigzag(&mut self, value: i64) -> std::io::Result<()> {
+        self.write_uleb128(((value << 1) ^ (value >> 63)) as u64)
+    }
+
+    fn write_prefix_varint(&mut self, value: u64) -> std::io::Result<()> {
+        let len = prefix_varint_len(value);
+        if len == 9 {
+            self.write_u8(0)?;
+            return self.write_all(&value.to_le_bytes());
+        }
+        let encoded = (value << len) | (1 << (len - 1));
+        self.write_all(&encoded.to_le_bytes()[..len])
+    }
+
+    /// Writes a UTF-8 string the way .NET's `BinaryWriter.Write(string)` does. Strings longer than
+    /// `i32::MAX` bytes are rejected with `InvalidInput`.
+    fn write_dotnet_string(&mut self, string: &str) -> std::io::Result<()> {
+        let len = i32::try_from(string.len())
+            .map_err(|_| std::io::Error::new(std::io::ErrorKind::InvalidInput, "string too long for a 7-bit encoded i32 length"))?;
+        self.write_7bit_encoded_i32(len)?;
+        self.write_all(string.as_bytes())
+    }
+}
+
+impl<W: WriteBytesExt + ?Sized> BinaryWriter for W {}
+
+pub trait BinaryPoker: WriteBytesExt + Seek {
+
+    /// Seeks to position from start of the stream and writes `bytes` then returns to original position
+    fn poke_bytes(&mut self, position: u64, bytes: &[u8]) -> std::io::Result<()> {
+        let start = self.stream_position()?;
+        self.seek(SeekFrom::Start(position))?;
+        let result = self.write_all(bytes);
+        self.seek(SeekFrom::Start(start))?;
+        result
+    }
+
+    /// Writes `fill` bytes until the position is a multiple of `alignment`.
+    fn pad_to(&mut self, alignment: u64, fill: u8) -> std::io::Result<()> {
+        if alignment <= 1 {
+            return Ok(());
+        }
+        let position = self.stream_position()?;
+        let padding = position.next_multiple_of(alignment) - position;
+        self.write_all(&vec![fill; padding as usize])
+    }
+
+    fn poke_u8(&mut self, position: u64, value: u8) -> std::io::Result<()> {
+        let start = self.stream_position()?;
+        self.seek(SeekFrom::Start(position))?;
+        let result = self.write_u8(value);
+        self.seek(SeekFrom::Start(start))?;
+        result
+    }
+
+    fn poke_i8(&mut self, position: u64, value: i8) -> std::io::Result<()> {
+        let start = self.stream_position()?;
+        self.seek(SeekFrom::Start(position))?;
+        let result = self.write_i8(value);
+        self.seek(SeekFrom::Start(start))?;
+        result
+    }
+    poke_type!(u16);
+    poke_type!(i16);
+    poke_type!(u32);
+    poke_type!(i32);
+    poke_type!(u64);
+    poke_type!(i64);
+    poke_type!(u128);
+    poke_type!(i128);
+    poke_type!(f32);
+    poke_type!(f64);
+}
+
+impl<W: WriteBytesExt + Seek> BinaryPoker for W {}
@@ -0,0 +1,151 @@
+use std::io::{Read, Seek, SeekFrom, Error, ErrorKind};
+use byteorder::ReadBytesExt;
+
+/// Order in which the bits of each byte are consumed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BitOrder {
+    /// The first bit is the most significant bit of the byte and ends up as the most significant bit of the value.
+    MsbFirst,
+    /// The first bit is the least significant bit of the byte and ends up as the least significant bit of the value.
+    LsbFirst,
+}
+
+/// Reads values of arbitrary bit widths from a byte stream.
+///
+/// Whole bytes are pulled from the inner reader only when needed, so once the reader is aligned the inner
+/// stream sits exactly at the next unread byte. `BitReader` also implements `Read`, so every `BinaryReader`
+/// method can be used on it directly.
+pub struct BitReader<R> {
+    inner: R,
+    order: BitOrder,
+    current: u8,
+    bits_left: u32,
+}
+
+impl<R: Read> BitReader<R> {
+    pub fn new(inner: R, order: BitOrder) -> Self {
+        BitReader { inner, order, current: 0, bits_left: 0 }
+    }
+
+    pub fn order(&self) -> BitOrder {
+        self.order
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Returns the inner reader. Reading from it while unaligned skips the rest of the current byte.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    pub fn is_aligned(&self) -> bool {
+        self.bits_left == 0
+    }
+
+    /// Discards the remaining bits of the current byte.
+    pub fn align(&mut self) {
+        self.bits_left = 0;
+    }
+
+    /// Reads `count` bits, at most 64, as an unsigned value.
+    pub fn read_bits(&mut self, count: u32) -> std::io::Result<u64> {
+        if count > 64 {
+            return Err(Error::new(ErrorKind::InvalidInput, "cannot read more than 64 bits at once"));
+        }
+        let mut value = 0u64;
+        let mut read = 0;
+        while read < count {
+            if self.bits_left == 0 {
+                self.current = self.inner.read_u8()?;
+                self.bits_left = 8;
+            }
+            let take = self.bits_left.min(count - read);
+            let mask = ((1u16 << take) - 1) as u8;
+            match self.order {
+                BitOrder::MsbFirst => {
+                    let bits = (self.current >> (self.bits_left - take)) & mask;
+                    value = (value << take) | bits as u64;
+                }
+                BitOrder::LsbFirst => {
+                    let bits = (self.current >> (8 - self.bits_left)) & mask;
+                    value |= (bits as u64) << read;
+                }
+            }
+            self.bits_left -= take;
+            read += take;
+        }
+        Ok(value)
+    }
+
+    /// Reads `count` bits, at most 64, as a two's complement signed value.
+    pub fn read_signed_bits(&mut self, count: u32) -> std::io::Result<i64> {
+        let value = self.read_bits(count)?;
+        if count == 0 || count == 64 {
+            return Ok(value as i64);
+        }
+        let shift = 64 - count;
+        Ok(((value << shift) as i64) >> shift)
+    }
+
+    pub fn read_bool(&mut self) -> std::io::Result<bool> {
+        Ok(self.read_bits(1)? != 0)
+    }
+}
+
+impl<R: Read + Seek> BitReader<R> {
+    /// Position of the next unread bit from the start of the stream.
+    pub fn bit_position(&mut self) -> std::io::Result<u64> {
+        Ok(self.inner.stream_position()? * 8 - self.bits_left as u64)
+    }
+
+    /// Seeks to `position` bits from the start of the stream.
+    pub fn seek_bits(&mut self, position: u64) -> std::io::Result<()> {
+        self.inner.seek(SeekFrom::Start(position / 8))?;
+        self.bits_left = 0;
+        let offset = (position % 8) as u32;
+        if offset != 0 {
+            self.current = self.inner.read_u8()?;
+            self.bits_left = 8 - offset;
+        }
+        Ok(())
+    }
+
+    /// Seeks to `position` bits from the start of the stream and reads `count` bits then returns to original position
+    pub fn peek_bits(&mut self, position: u64, count: u32) -> std::io::Result<u64> {
+        let start = self.bit_position()?;
+        self.seek_bits(position)?;
+        let bits = self.read_bits(count);
+        self.seek_bits(start)?;
+        bits
+    }
+
+    pub fn peek_signed_bits(&mut self, position: u64, count: u32) -> std::io::Result<i64> {
+        let start = self.bit_position()?;
+        self.seek_bits(position)?;
+        let bits = self.read_signed_bits(count);
+        self.seek_bits(start)?;
+        bits
+    }
+}
+
+impl<R: Read> Read for BitReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.is_aligned() {
+            return self.inner.read(buf);
+        }
+        for (i, byte) in buf.iter_mut().enumerate() {
+            match self.read_bits(8) {
+                Ok(bits) => *byte = bits as u8,
+                Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(i),
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(buf.len())
+    }
+}
@@ -0,0 +1,104 @@
+use std::io::{Write, Error, ErrorKind};
+use byteorder::WriteBytesExt;
+use crate::bit_reader::BitOrder;
+
+/// Writes values of arbitrary bit widths to a byte stream. The counterpart of `BitReader`.
+///
+/// A byte is handed to the inner writer once all 8 of its bits are written. Call `align` or `into_inner`
+/// to zero pad and write out a partial byte.
+pub struct BitWriter<W> {
+    inner: W,
+    order: BitOrder,
+    current: u8,
+    bits_used: u32,
+}
+
+impl<W: Write> BitWriter<W> {
+    pub fn new(inner: W, order: BitOrder) -> Self {
+        BitWriter { inner, order, current: 0, bits_used: 0 }
+    }
+
+    pub fn order(&self) -> BitOrder {
+        self.order
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    pub fn is_aligned(&self) -> bool {
+        self.bits_used == 0
+    }
+
+    /// Pads the current byte with zero bits and writes it out.
+    pub fn align(&mut self) -> std::io::Result<()> {
+        if self.bits_used != 0 {
+            self.inner.write_u8(self.current)?;
+            self.current = 0;
+            self.bits_used = 0;
+        }
+        Ok(())
+    }
+
+    /// Aligns the writer and returns the inner writer.
+    pub fn into_inner(mut self) -> std::io::Result<W> {
+        self.align()?;
+        Ok(self.inner)
+    }
+
+    /// Writes the low `count` bits, at most 64, of `value`.
+    pub fn write_bits(&mut self, value: u64, count: u32) -> std::io::Result<()> {
+        if count > 64 {
+            return Err(Error::new(ErrorKind::InvalidInput, "cannot write more than 64 bits at once"));
+        }
+        let mut written = 0;
+        while written < count {
+            let space = 8 - self.bits_used;
+            let take = space.min(count - written);
+            let mask = (1u64 << take) - 1;
+            match self.order {
+                BitOrder::MsbFirst => {
+                    let bits = (value >> (count - written - take)) & mask;
+                    self.current |= (bits as u8) << (space - take);
+                }
+                BitOrder::LsbFirst => {
+                    let bits = (value >> written) & mask;
+                    self.current |= (bits as u8) << self.bits_used;
+                }
+            }
+            self.bits_used += take;
+            written += take;
+            if self.bits_used == 8 {
+                self.inner.write_u8(self.current)?;
+                self.current = 0;
+                self.bits_used = 0;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn write_signed_bits(&mut self, value: i64, count: u32) -> std::io::Result<()> {
+        self.write_bits(value as u64, count)
+    }
+
+    pub fn write_bool(&mut self, value: bool) -> std::io::Result<()> {
+        self.write_bits(value as u64, 1)
+    }
+}
+
+impl<W: Write> Write for BitWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if self.is_aligned() {
+            return self.inner.write(buf);
+        }
+        for &byte in buf {
+            self.write_bits(byte as u64, 8)?;
+        }
+        Ok(buf.len())
+    }
+
+    /// Flushes the inner writer. A partial byte is kept until the writer is aligned.
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
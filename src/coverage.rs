@@ -0,0 +1,120 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::ops::Range;
+use crate::recording::ReadRecord;
+
+/// A byte range that more than one recorded read covered, and the types it was read as.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Conflict {
+    pub range: Range<u64>,
+    /// The distinct types the range was read as, sorted by name. In `Coverage::overlaps`, untyped reads are
+    /// listed as `bytes`.
+    pub types: Vec<&'static str>,
+}
+
+/// Which bytes of a stream were never read, which were read as conflicting types, and which were read
+/// more than once.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Coverage {
+    pub len: u64,
+    pub read: u64,
+    pub unread: Vec<Range<u64>>,
+    /// Ranges read as two or more different types. Untyped reads are left out.
+    pub conflicts: Vec<Conflict>,
+    /// Ranges covered by two or more reads of any type, such as a peek followed by a read of the same field.
+    pub overlaps: Vec<Conflict>,
+}
+
+impl Coverage {
+    /// Builds the coverage of a `len` byte stream from the reads recorded over it.
+    pub fn new(records: &[ReadRecord], len: u64) -> Coverage {
+        let mut spans: Vec<Range<u64>> = records.iter()
+            .filter(|r| r.len > 0 && r.offset < len)
+            .map(|r| r.offset..r.end().min(len))
+            .collect();
+        spans.sort_by_key(|r| r.start);
+
+        let mut unread = Vec::new();
+        let mut read = 0;
+        let mut covered_to = 0;
+        for span in spans {
+            if span.start > covered_to {
+                unread.push(covered_to..span.start);
+            }
+            if span.end > covered_to {
+                read += span.end - covered_to.max(span.start);
+                covered_to = span.end;
+            }
+        }
+        if covered_to < len {
+            unread.push(covered_to..len);
+        }
+
+        let (conflicts, overlaps) = overlaps(records);
+        Coverage { len, read, unread, conflicts, overlaps }
+    }
+
+    pub fn unread_bytes(&self) -> u64 {
+        self.len - self.read
+    }
+}
+
+/// Appends `range` read as `types` to `ranges`, extending the last entry when it continues it.
+fn push_range(ranges: &mut Vec<Conflict>, range: Range<u64>, types: Vec<&'static str>) {
+    match ranges.last_mut() {
+        Some(last) if last.range.end == range.start && last.types == types => last.range.end = range.end,
+        _ => ranges.push(Conflict { range, types }),
+    }
+}
+
+/// Returns the ranges read as different types, and the ranges read more than once.
+fn overlaps(records: &[ReadRecord]) -> (Vec<Conflict>, Vec<Conflict>) {
+    // Each record opens at its offset and closes at its end, swept in offset order.
+    let mut events: BTreeMap<u64, Vec<(usize, bool)>> = BTreeMap::new();
+    for (i, record) in records.iter().enumerate().filter(|(_, r)| r.len > 0) {
+        events.entry(record.offset).or_default().push((i, true));
+        events.entry(record.end()).or_default().push((i, false));
+    }
+
+    let mut active: BTreeSet<usize> = BTreeSet::new();
+    let mut conflicts: Vec<Conflict> = Vec::new();
+    let mut overlaps: Vec<Conflict> = Vec::new();
+    let mut points = events.into_iter().peekable();
+    while let Some((offset, changes)) = points.next() {
+        for (i, opens) in changes {
+            if opens {
+                active.insert(i);
+            } else {
+                active.remove(&i);
+            }
+        }
+        let Some(&(end, _)) = points.peek() else { break };
+        if active.len() < 2 {
+            continue;
+        }
+        let types: BTreeSet<&'static str> = active.iter().map(|&i| records[i].type_name.unwrap_or("bytes")).collect();
+        let typed: Vec<&'static str> = active.iter().filter_map(|&i| records[i].type_name).collect::<BTreeSet<_>>().into_iter().collect();
+        if typed.len() >= 2 {
+            push_range(&mut conflicts, offset..end, typed);
+        }
+        push_range(&mut overlaps, offset..end, types.into_iter().collect());
+    }
+    (conflicts, overlaps)
+}
+
+impl fmt::Display for Coverage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let percent = if self.len == 0 { 100.0 } else { self.read as f64 * 100.0 / self.len as f64 };
+        writeln!(f, "{} of {} bytes read ({:.1}%)", self.read, self.len, percent)?;
+        for range in &self.unread {
+            writeln!(f, "unread   {:#010x}..{:#010x} ({} bytes)", range.start, range.end, range.end - range.start)?;
+        }
+        for conflict in &self.conflicts {
+            writeln!(f, "conflict {:#010x}..{:#010x} read as {}", conflict.range.start, conflict.range.end, conflict.types.join(", "))?;
+        }
+        for overlap in &self.overlaps {
+            writeln!(f, "overlap  {:#010x}..{:#010x} read as {}", overlap.range.start, overlap.range.end, overlap.types.join(", "))?;
+        }
+        Ok(())
+    }
+}
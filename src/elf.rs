@@ -0,0 +1,403 @@
+use std::io::{Read, Seek, SeekFrom};
+use crate::address::PointerWidth;
+use crate::binary_reader::{BinaryPeeker, BinaryReader};
+use crate::encoding::Encoding;
+use crate::endian::Endian;
+use crate::error::InterpreterError;
+use crate::va_reader::{Segment, VaReader};
+
+pub const ELF_MAGIC: &[u8; 4] = b"\x7FELF";
+
+pub const PT_LOAD: u32 = 1;
+pub const PT_DYNAMIC: u32 = 2;
+pub const PT_INTERP: u32 = 3;
+
+pub const SHT_SYMTAB: u32 = 2;
+pub const SHT_STRTAB: u32 = 3;
+pub const SHT_DYNAMIC: u32 = 6;
+pub const SHT_DYNSYM: u32 = 11;
+
+pub const DT_NULL: i64 = 0;
+pub const DT_NEEDED: i64 = 1;
+pub const DT_STRTAB: i64 = 5;
+pub const DT_SONAME: i64 = 14;
+
+/// Section index meaning the real value did not fit and is stored in the first section header.
+const SHN_XINDEX: u16 = 0xFFFF;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ElfHeader {
+    pub width: PointerWidth,
+    pub endian: Endian,
+    pub os_abi: u8,
+    /// `ET_*`, e.g. 2 for an executable and 3 for a shared object or position independent executable.
+    pub kind: u16,
+    pub machine: u16,
+    pub version: u32,
+    pub entry: u64,
+    pub program_header_offset: u64,
+    pub section_header_offset: u64,
+    pub flags: u32,
+    pub program_header_size: u16,
+    pub program_header_count: u16,
+    pub section_header_size: u16,
+    /// Taken from the first section header when the file has too many sections for the ELF header.
+    pub section_header_count: u32,
+    pub section_name_index: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ProgramHeader {
+    /// `PT_*`.
+    pub kind: u32,
+    pub flags: u32,
+    pub offset: u64,
+    pub vaddr: u64,
+    pub paddr: u64,
+    pub file_size: u64,
+    pub mem_size: u64,
+    pub align: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Section {
+    /// Resolved from the section name string table, empty if the file has none.
+    pub name: String,
+    pub name_offset: u32,
+    /// `SHT_*`.
+    pub kind: u32,
+    pub flags: u64,
+    pub addr: u64,
+    pub offset: u64,
+    pub size: u64,
+    pub link: u32,
+    pub info: u32,
+    pub addralign: u64,
+    pub entsize: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Symbol {
+    pub name: String,
+    pub value: u64,
+    pub size: u64,
+    pub info: u8,
+    pub other: u8,
+    pub section_index: u16,
+}
+
+impl Symbol {
+    /// `STB_*`, e.g. 0 for local and 1 for global.
+    pub fn bind(&self) -> u8 {
+        self.info >> 4
+    }
+
+    /// `STT_*`, e.g. 1 for an object and 2 for a function.
+    pub fn kind(&self) -> u8 {
+        self.info & 0xF
+    }
+
+    pub fn is_defined(&self) -> bool {
+        self.section_index != 0
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Dynamic {
+    /// `DT_*`.
+    pub tag: i64,
+    pub value: u64,
+}
+
+/// The headers of an ELF file. Byte order and pointer width come from the file, so every field is read
+/// with the runtime `_endian` methods. Reading the tables they point at takes the file again.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Elf {
+    pub header: ElfHeader,
+    pub program_headers: Vec<ProgramHeader>,
+    pub sections: Vec<Section>,
+}
+
+/// Errors with `Malformed` unless `count` entries of `size` bytes at `offset` fit in the file.
+fn check_table<R: BinaryPeeker + ?Sized>(reader: &mut R, what: &'static str, offset: u64, count: u64, size: u64) -> Result<(), InterpreterError> {
+    let end = count.checked_mul(size).and_then(|len| len.checked_add(offset));
+    match end {
+        Some(end) if end <= reader.total_len()? => Ok(()),
+        _ => Err(InterpreterError::Malformed { what, offset }),
+    }
+}
+
+impl Elf {
+    /// Parses the headers of the file at the start of `reader`. The cursor is left where it was.
+    pub fn parse<R: BinaryPeeker + ?Sized>(reader: &mut R) -> Result<Elf, InterpreterError> {
+        let start = reader.stream_position()?;
+        let elf = Elf::parse_headers(reader);
+        reader.seek(SeekFrom::Start(start))?;
+        elf
+    }
+
+    fn parse_headers<R: BinaryPeeker + ?Sized>(reader: &mut R) -> Result<Elf, InterpreterError> {
+        reader.peek_magic(0, ELF_MAGIC)?;
+        let width = match reader.peek_u8(4)? {
+            1 => PointerWidth::U32,
+            2 => PointerWidth::U64,
+            _ => return Err(InterpreterError::Malformed { what: "ELF class", offset: 4 }),
+        };
+        let endian = match reader.peek_u8(5)? {
+            1 => Endian::Little,
+            2 => Endian::Big,
+            _ => return Err(InterpreterError::Malformed { what: "ELF data encoding", offset: 5 }),
+        };
+        let os_abi = reader.peek_u8(7)?;
+
+        reader.seek(SeekFrom::Start(16))?;
+        let kind = reader.read_u16_endian(endian)?;
+        let machine = reader.read_u16_endian(endian)?;
+        let version = reader.read_u32_endian(endian)?;
+        let entry = reader.read_pointer_endian(width, endian)?;
+        let program_header_offset = reader.read_pointer_endian(width, endian)?;
+        let section_header_offset = reader.read_pointer_endian(width, endian)?;
+        let flags = reader.read_u32_endian(endian)?;
+        reader.skip(2)?;
+        let sizes_at = reader.stream_position()?;
+        let program_header_size = reader.read_u16_endian(endian)?;
+        let program_header_count = reader.read_u16_endian(endian)?;
+        let section_header_size = reader.read_u16_endian(endian)?;
+        let mut section_header_count = reader.read_u16_endian(endian)? as u32;
+        let short_name_index = reader.read_u16_endian(endian)?;
+        let mut section_name_index = short_name_index as u32;
+
+        let (min_program_header, min_section_header) = match width {
+            PointerWidth::U32 => (32, 40),
+            PointerWidth::U64 => (56, 64),
+        };
+        if program_header_count > 0 && program_header_size < min_program_header {
+            return Err(InterpreterError::Malformed { what: "program header size", offset: sizes_at });
+        }
+        if section_header_offset != 0 {
+            if section_header_size < min_section_header {
+                return Err(InterpreterError::Malformed { what: "section header size", offset: sizes_at + 4 });
+            }
+            let first = Elf::read_section(reader, section_header_offset, width, endian)?;
+            if section_header_count == 0 {
+                section_header_count = u32::try_from(first.size)
+                    .map_err(|_| InterpreterError::Malformed { what: "section count", offset: section_header_offset })?;
+            }
+            if short_name_index == SHN_XINDEX {
+                section_name_index = first.link;
+            }
+        }
+
+        check_table(reader, "program header table", program_header_offset, program_header_count as u64, program_header_size as u64)?;
+        let mut program_headers = Vec::with_capacity(program_header_count as usize);
+        for index in 0..program_header_count as u64 {
+            reader.seek(SeekFrom::Start(program_header_offset + index * program_header_size as u64))?;
+            program_headers.push(match width {
+                PointerWidth::U32 => {
+                    let kind = reader.read_u32_endian(endian)?;
+                    let offset = reader.read_u32_endian(endian)? as u64;
+                    let vaddr = reader.read_u32_endian(endian)? as u64;
+                    let paddr = reader.read_u32_endian(endian)? as u64;
+                    let file_size = reader.read_u32_endian(endian)? as u64;
+                    let mem_size = reader.read_u32_endian(endian)? as u64;
+                    let flags = reader.read_u32_endian(endian)?;
+                    let align = reader.read_u32_endian(endian)? as u64;
+                    ProgramHeader { kind, flags, offset, vaddr, paddr, file_size, mem_size, align }
+                }
+                PointerWidth::U64 => ProgramHeader {
+                    kind: reader.read_u32_endian(endian)?,
+                    flags: reader.read_u32_endian(endian)?,
+                    offset: reader.read_u64_endian(endian)?,
+                    vaddr: reader.read_u64_endian(endian)?,
+                    paddr: reader.read_u64_endian(endian)?,
+                    file_size: reader.read_u64_endian(endian)?,
+                    mem_size: reader.read_u64_endian(endian)?,
+                    align: reader.read_u64_endian(endian)?,
+                },
+            });
+        }
+
+        let section_count = if section_header_offset == 0 { 0 } else { section_header_count };
+        check_table(reader, "section header table", section_header_offset, section_count as u64, section_header_size as u64)?;
+        let mut sections = Vec::with_capacity(section_count as usize);
+        for index in 0..section_count as u64 {
+            sections.push(Elf::read_section(reader, section_header_offset + index * section_header_size as u64, width, endian)?);
+        }
+        if let Some(names) = sections.get(section_name_index as usize).filter(|_| section_name_index != 0).cloned() {
+            for section in &mut sections {
+                section.name = Elf::read_string(reader, &names, section.name_offset)?;
+            }
+        }
+
+        let header = ElfHeader {
+            width,
+            endian,
+            os_abi,
+            kind,
+            machine,
+            version,
+            entry,
+            program_header_offset,
+            section_header_offset,
+            flags,
+            program_header_size,
+            program_header_count,
+            section_header_size,
+            section_header_count,
+            section_name_index,
+        };
+        Ok(Elf { header, program_headers, sections })
+    }
+
+    fn read_section<R: BinaryPeeker + ?Sized>(reader: &mut R, position: u64, width: PointerWidth, endian: Endian) -> Result<Section, InterpreterError> {
+        reader.seek(SeekFrom::Start(position))?;
+        Ok(Section {
+            name: String::new(),
+            name_offset: reader.read_u32_endian(endian)?,
+            kind: reader.read_u32_endian(endian)?,
+            flags: reader.read_pointer_endian(width, endian)?,
+            addr: reader.read_pointer_endian(width, endian)?,
+            offset: reader.read_pointer_endian(width, endian)?,
+            size: reader.read_pointer_endian(width, endian)?,
+            link: reader.read_u32_endian(endian)?,
+            info: reader.read_u32_endian(endian)?,
+            addralign: reader.read_pointer_endian(width, endian)?,
+            entsize: reader.read_pointer_endian(width, endian)?,
+        })
+    }
+
+    fn read_string<R: BinaryPeeker + ?Sized>(reader: &mut R, table: &Section, offset: u32) -> Result<String, InterpreterError> {
+        if offset as u64 >= table.size {
+            return Err(InterpreterError::Malformed { what: "string table offset", offset: table.offset });
+        }
+        reader.peek_cstr_encoded(table.offset + offset as u64, Encoding::Latin1)
+    }
+
+    pub fn endian(&self) -> Endian {
+        self.header.endian
+    }
+
+    pub fn pointer_width(&self) -> PointerWidth {
+        self.header.width
+    }
+
+    pub fn section_named(&self, name: &str) -> Option<&Section> {
+        self.sections.iter().find(|s| s.name == name)
+    }
+
+    pub fn program_header(&self, kind: u32) -> Option<&ProgramHeader> {
+        self.program_headers.iter().find(|p| p.kind == kind)
+    }
+
+    /// The string at `offset` in the string table section `table`.
+    pub fn string_at<R: BinaryPeeker + ?Sized>(&self, file: &mut R, table: &Section, offset: u32) -> Result<String, InterpreterError> {
+        Elf::read_string(file, table, offset)
+    }
+
+    /// Reads the symbol table in `section`, naming symbols from the string table it links to.
+    pub fn section_symbols<R: BinaryPeeker + ?Sized>(&self, file: &mut R, section: &Section) -> Result<Vec<Symbol>, InterpreterError> {
+        let (width, endian) = (self.header.width, self.header.endian);
+        let entry_size = match width {
+            PointerWidth::U32 => 16,
+            PointerWidth::U64 => 24,
+        };
+        let names = self.sections.get(section.link as usize)
+            .filter(|s| s.kind == SHT_STRTAB)
+            .ok_or(InterpreterError::Malformed { what: "symbol string table link", offset: section.offset })?;
+        let stride = section.entsize.max(entry_size);
+        let count = section.size / stride;
+        check_table(file, "symbol table", section.offset, count, stride)?;
+
+        let start = file.stream_position()?;
+        let mut raw = Vec::with_capacity(count as usize);
+        for index in 0..count {
+            file.seek(SeekFrom::Start(section.offset + index * stride))?;
+            let name = file.read_u32_endian(endian)?;
+            raw.push(match width {
+                PointerWidth::U32 => {
+                    let value = file.read_u32_endian(endian)? as u64;
+                    let size = file.read_u32_endian(endian)? as u64;
+                    (name, value, size, file.read_u8()?, file.read_u8()?, file.read_u16_endian(endian)?)
+                }
+                PointerWidth::U64 => {
+                    let (info, other, section_index) = (file.read_u8()?, file.read_u8()?, file.read_u16_endian(endian)?);
+                    (name, file.read_u64_endian(endian)?, file.read_u64_endian(endian)?, info, other, section_index)
+                }
+            });
+        }
+        file.seek(SeekFrom::Start(start))?;
+
+        raw.into_iter()
+            .map(|(name, value, size, info, other, section_index)| Ok(Symbol {
+                name: if name == 0 { String::new() } else { Elf::read_string(file, names, name)? },
+                value,
+                size,
+                info,
+                other,
+                section_index,
+            }))
+            .collect()
+    }
+
+    /// Every symbol in sections of `kind`, `SHT_SYMTAB` for the full table or `SHT_DYNSYM` for the
+    /// dynamic one. Stripped files only have the latter.
+    pub fn symbols<R: BinaryPeeker + ?Sized>(&self, file: &mut R, kind: u32) -> Result<Vec<Symbol>, InterpreterError> {
+        let mut symbols = Vec::new();
+        for section in self.sections.iter().filter(|s| s.kind == kind) {
+            symbols.extend(self.section_symbols(file, section)?);
+        }
+        Ok(symbols)
+    }
+
+    /// The entries of the `PT_DYNAMIC` segment, up to `DT_NULL`. Empty for static files.
+    pub fn dynamic<R: BinaryPeeker + ?Sized>(&self, file: &mut R) -> Result<Vec<Dynamic>, InterpreterError> {
+        let Some(segment) = self.program_header(PT_DYNAMIC) else { return Ok(Vec::new()) };
+        let (width, endian) = (self.header.width, self.header.endian);
+        let entry_size = 2 * width.bytes() as u64;
+        let count = segment.file_size / entry_size;
+        check_table(file, "dynamic segment", segment.offset, count, entry_size)?;
+        let mut entries = Vec::new();
+        for index in 0..count {
+            let position = segment.offset + index * entry_size;
+            let tag = match width {
+                PointerWidth::U32 => file.peek_i32_endian(position, endian)? as i64,
+                PointerWidth::U64 => file.peek_i64_endian(position, endian)?,
+            };
+            if tag == DT_NULL {
+                break;
+            }
+            let value = file.peek_pointer_endian(position + width.bytes() as u64, width, endian)?;
+            entries.push(Dynamic { tag, value });
+        }
+        Ok(entries)
+    }
+
+    /// Names of the libraries listed as `DT_NEEDED`, in load order.
+    pub fn needed<R: BinaryPeeker + ?Sized>(&self, file: &mut R) -> Result<Vec<String>, InterpreterError> {
+        let entries = self.dynamic(file)?;
+        let Some(strings) = entries.iter().find(|d| d.tag == DT_STRTAB) else { return Ok(Vec::new()) };
+        // DT_STRTAB is an address, so the strings are read through the loaded segments.
+        let mut image = VaReader::new(file, self.segments());
+        entries.iter()
+            .filter(|d| d.tag == DT_NEEDED)
+            .map(|d| {
+                let name = strings.value.checked_add(d.value)
+                    .ok_or(InterpreterError::Malformed { what: "DT_NEEDED string offset", offset: strings.value })?;
+                image.peek_cstr_encoded(name, Encoding::Latin1)
+            })
+            .collect()
+    }
+
+    /// The `PT_LOAD` segments at their link time addresses, for a `VaReader`.
+    pub fn segments(&self) -> Vec<Segment> {
+        self.program_headers.iter()
+            .filter(|p| p.kind == PT_LOAD)
+            .map(|p| Segment::new(p.vaddr, p.mem_size, p.offset, p.file_size.min(p.mem_size)))
+            .collect()
+    }
+
+    /// Wraps the file in a reader addressed by virtual address, as the file would be loaded.
+    pub fn va_reader<R: Read + Seek>(&self, file: R) -> VaReader<R> {
+        VaReader::new(file, self.segments())
+    }
+}
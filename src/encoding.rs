@@ -0,0 +1,106 @@
+use crate::error::InterpreterError;
+
+/// Text encodings understood by the `*_encoded` string readers and writers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Encoding {
+    Utf8,
+    Utf16LE,
+    Utf16BE,
+    Latin1,
+    /// A Windows code page such as 932 (Shift-JIS) or 1252. Requires the `code-pages` feature.
+    #[cfg(feature = "code-pages")]
+    CodePage(u16),
+}
+
+impl Encoding {
+    #[cfg(feature = "code-pages")]
+    pub const SHIFT_JIS: Encoding = Encoding::CodePage(932);
+    #[cfg(feature = "code-pages")]
+    pub const WINDOWS_1252: Encoding = Encoding::CodePage(1252);
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "UTF-8",
+            Encoding::Utf16LE => "UTF-16LE",
+            Encoding::Utf16BE => "UTF-16BE",
+            Encoding::Latin1 => "ISO-8859-1",
+            #[cfg(feature = "code-pages")]
+            Encoding::CodePage(cp) => code_page(*cp).map(|e| e.name()).unwrap_or("unknown code page"),
+        }
+    }
+
+    /// Size in bytes of one code unit, which is also the width of the NUL terminator.
+    pub fn unit_size(&self) -> usize {
+        match self {
+            Encoding::Utf16LE | Encoding::Utf16BE => 2,
+            _ => 1,
+        }
+    }
+
+    pub fn decode(&self, bytes: &[u8]) -> Result<String, InterpreterError> {
+        match self {
+            Encoding::Utf8 => Ok(String::from_utf8(bytes.to_vec())?),
+            Encoding::Utf16LE | Encoding::Utf16BE => {
+                if !bytes.len().is_multiple_of(2) {
+                    return Err(InterpreterError::DecodeError(self.name()));
+                }
+                let units: Vec<u16> = bytes.chunks_exact(2)
+                    .map(|c| match self {
+                        Encoding::Utf16LE => u16::from_le_bytes([c[0], c[1]]),
+                        _ => u16::from_be_bytes([c[0], c[1]]),
+                    })
+                    .collect();
+                Ok(String::from_utf16(&units)?)
+            }
+            Encoding::Latin1 => Ok(bytes.iter().map(|&b| b as char).collect()),
+            #[cfg(feature = "code-pages")]
+            Encoding::CodePage(cp) => {
+                let encoding = code_page(*cp)?;
+                encoding.decode_without_bom_handling_and_without_replacement(bytes)
+                    .map(|s| s.into_owned())
+                    .ok_or(InterpreterError::DecodeError(encoding.name()))
+            }
+        }
+    }
+
+    pub fn encode(&self, string: &str) -> Result<Vec<u8>, InterpreterError> {
+        match self {
+            Encoding::Utf8 => Ok(string.as_bytes().to_vec()),
+            Encoding::Utf16LE => Ok(string.encode_utf16().flat_map(u16::to_le_bytes).collect()),
+            Encoding::Utf16BE => Ok(string.encode_utf16().flat_map(u16::to_be_bytes).collect()),
+            Encoding::Latin1 => string.chars()
+                .map(|c| u8::try_from(c).map_err(|_| InterpreterError::EncodeError(self.name())))
+                .collect(),
+            #[cfg(feature = "code-pages")]
+            Encoding::CodePage(cp) => {
+                let encoding = code_page(*cp)?;
+                let (bytes, _, had_errors) = encoding.encode(string);
+                if had_errors {
+                    return Err(InterpreterError::EncodeError(encoding.name()));
+                }
+                Ok(bytes.into_owned())
+            }
+        }
+    }
+}
+
+#[cfg(feature = "code-pages")]
+fn code_page(cp: u16) -> Result<&'static encoding_rs::Encoding, InterpreterError> {
+    Ok(match cp {
+        874 => encoding_rs::WINDOWS_874,
+        932 => encoding_rs::SHIFT_JIS,
+        936 => encoding_rs::GBK,
+        949 => encoding_rs::EUC_KR,
+        950 => encoding_rs::BIG5,
+        1250 => encoding_rs::WINDOWS_1250,
+        1251 => encoding_rs::WINDOWS_1251,
+        1252 => encoding_rs::WINDOWS_1252,
+        1253 => encoding_rs::WINDOWS_1253,
+        1254 => encoding_rs::WINDOWS_1254,
+        1255 => encoding_rs::WINDOWS_1255,
+        1256 => encoding_rs::WINDOWS_1256,
+        1257 => encoding_rs::WINDOWS_1257,
+        1258 => encoding_rs::WINDOWS_1258,
+        _ => return Err(InterpreterError::UnsupportedCodePage(cp)),
+    })
+}
@@ -0,0 +1,12 @@
+/// Byte order chosen at runtime, for formats that declare their endianness in a header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Endian {
+    Little,
+    Big,
+}
+
+impl Endian {
+    pub fn native() -> Endian {
+        if cfg!(target_endian = "little") { Endian::Little } else { Endian::Big }
+    }
+}
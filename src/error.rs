@@ -5,10 +5,66 @@ use thiserror::Error as ThisError;
 #[derive(ThisError, Debug)]
 pub enum InterpreterError {
     #[error(transparent)]
-    IoError(#[from] std::io::Error),
+    IoError(std::io::Error),
     #[error(transparent)]
     Utf8Error(#[from] FromUtf8Error),
     #[error(transparent)]
-    Utf16Error(#[from] FromUtf16Error)
+    Utf16Error(#[from] FromUtf16Error),
+    #[error("invalid {0} data")]
+    DecodeError(&'static str),
+    #[error("string cannot be represented in {0}")]
+    EncodeError(&'static str),
+    #[error("unsupported code page {0}")]
+    UnsupportedCodePage(u16),
+    #[error("string of {len} code units does not fit in {max}")]
+    StringTooLong { len: usize, max: usize },
+    #[error("allocation of {size} bytes exceeds the limit of {limit} bytes")]
+    AllocationTooLarge { size: u64, limit: u64 },
+    #[error("variable length integer overflows {0} bits")]
+    VarintOverflow(u32),
+    #[error("overlong variable length integer encoding")]
+    VarintOverlong,
+    #[error("bad magic at {offset:#x}: expected {expected:02X?}, found {found:02X?}")]
+    BadMagic { expected: Vec<u8>, found: Vec<u8>, offset: u64 },
+    #[error("bad padding at {offset:#x}: expected {expected:#04x}, found {found:#04x}")]
+    BadPadding { offset: u64, expected: u8, found: u8 },
+    #[error("{needed} bytes needed at {offset:#x} but only {remaining} remain")]
+    Truncated { offset: u64, needed: u64, remaining: u64 },
+    #[error("bad pattern: {0}")]
+    BadPattern(String),
+    #[error("bad pointer expression: {0}")]
+    BadPointerExpr(String),
+    #[error("unknown symbol `{0}`")]
+    UnknownSymbol(String),
+    #[error("address {0:#x} is not mapped")]
+    UnmappedAddress(u64),
+    #[error("memory at {0:#x} cannot be read")]
+    UnreadableMemory(u64),
+    #[error("memory at {0:#x} cannot be written")]
+    UnwritableMemory(u64),
+    #[error("expected a call or jump at {offset:#x}, found opcode {opcode:#04x}")]
+    UnexpectedOpcode { offset: u64, opcode: u8 },
+    #[error("malformed {what} at {offset:#x}")]
+    Malformed { what: &'static str, offset: u64 },
+    #[error("unsupported {what} version {found}, expected {expected}")]
+    UnsupportedVersion { what: &'static str, found: u32, expected: u32 },
+    #[error("element {index}: {source}")]
+    Element { index: usize, #[source] source: Box<InterpreterError> },
 }
 
+impl InterpreterError {
+    /// Converts an `io::Error`. An error the crate raised inside one, e.g. `UnmappedAddress` from a `Read`
+    /// impl, is unwrapped back into itself.
+    pub fn from_io(e: std::io::Error) -> Self {
+        match e.get_ref().map(|inner| inner.is::<InterpreterError>()) {
+            Some(true) => *e.into_inner().unwrap().downcast::<InterpreterError>().unwrap(),
+            _ => InterpreterError::IoError(e),
+        }
+    }
+}
+
+impl From<std::io::Error> for InterpreterError {
+    fn from(e: std::io::Error) -> Self {
+        InterpreterError::from_io(e)
+    }
+}
@@ -0,0 +1,167 @@
+use std::ops::Range;
+use byteorder::ByteOrder;
+use crate::binary_reader::BinaryPeeker;
+use crate::encoding::Encoding;
+use crate::error::InterpreterError;
+
+/// Width of the slots an unknown region is split into.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SlotWidth {
+    Four,
+    Eight,
+}
+
+impl SlotWidth {
+    pub fn bytes(&self) -> usize {
+        match self {
+            SlotWidth::Four => 4,
+            SlotWidth::Eight => 8,
+        }
+    }
+}
+
+/// What an offset-like value points at.
+#[derive(Clone, Debug, PartialEq)]
+pub enum OffsetTarget {
+    String(String),
+    /// An address aligned to the slot width, as structures usually are.
+    Aligned,
+    Other,
+}
+
+/// One plausible interpretation of a slot.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Guess {
+    Padding,
+    SmallInt(i64),
+    Float(f64),
+    Offset { target: u64, points_to: OffsetTarget },
+    Utf8String(String),
+    Utf16String(String),
+}
+
+/// The interpretations of one slot, best first. Scores are in `0.0..=1.0`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SlotGuess {
+    pub offset: u64,
+    pub width: SlotWidth,
+    pub candidates: Vec<(Guess, f32)>,
+}
+
+impl SlotGuess {
+    pub fn best(&self) -> Option<&Guess> {
+        self.candidates.first().map(|(guess, _)| guess)
+    }
+}
+
+const MIN_STRING_CHARS: usize = 3;
+const MAX_STRING_BYTES: usize = 256;
+
+/// Peeks a NUL terminated run of printable text at `position`, if there is one.
+pub(crate) fn peek_text<R: BinaryPeeker + ?Sized>(reader: &mut R, position: u64, encoding: Encoding, len: u64) -> Option<String> {
+    let size = len.checked_sub(position)?.min(MAX_STRING_BYTES as u64) as usize;
+    let bytes = reader.peek_bytes(position, size).ok()?;
+    let unit = encoding.unit_size();
+    let end = bytes.chunks_exact(unit).position(|c| c.iter().all(|&b| b == 0))? * unit;
+    let text = encoding.decode(&bytes[..end]).ok()?;
+    let printable = text.chars().all(|c| !c.is_control() || c == '\t' || c == '\n' || c == '\r');
+    (printable && text.chars().count() >= MIN_STRING_CHARS).then_some(text)
+}
+
+/// A float is plausible when it has a sensible magnitude and few significant mantissa bits.
+fn float_score(value: f64, mantissa_bits: u32, trailing_zeros: u32) -> f32 {
+    if !value.is_finite() || value == 0.0 {
+        return 0.0;
+    }
+    let magnitude = value.abs();
+    if !(1e-5..=1e7).contains(&magnitude) {
+        return 0.0;
+    }
+    if trailing_zeros >= mantissa_bits / 2 { 0.8 } else { 0.4 }
+}
+
+fn small_int_score(value: i64) -> f32 {
+    match value.unsigned_abs() {
+        0 => 0.0,
+        1..=255 => 0.7,
+        256..=65535 => 0.5,
+        _ => 0.0,
+    }
+}
+
+/// The UTF-16 encoding with the same byte order as `E`.
+fn utf16<E: ByteOrder>() -> Encoding {
+    if E::read_u16(&[1, 0]) == 1 { Encoding::Utf16LE } else { Encoding::Utf16BE }
+}
+
+fn offset_guess<R: BinaryPeeker + ?Sized, E: ByteOrder>(reader: &mut R, target: u64, width: SlotWidth, len: u64) -> Option<(Guess, f32)> {
+    if target == 0 || target >= len {
+        return None;
+    }
+    let (points_to, score) = if let Some(text) = peek_text(reader, target, Encoding::Utf8, len) {
+        (OffsetTarget::String(text), 0.9)
+    } else if let Some(text) = peek_text(reader, target, utf16::<E>(), len) {
+        (OffsetTarget::String(text), 0.9)
+    } else if target.is_multiple_of(width.bytes() as u64) {
+        (OffsetTarget::Aligned, 0.5)
+    } else {
+        (OffsetTarget::Other, 0.2)
+    };
+    Some((Guess::Offset { target, points_to }, score))
+}
+
+/// Splits `range` into slots of `width` bytes stored in `E` order and scores each slot as padding,
+/// a small integer, a float, an offset into the stream, or the start of a string. UTF-16 text is read in
+/// `E` order too. Offsets are checked
+/// by peeking at their target, which is how a string table or aligned structure gets recognised.
+pub fn guess_fields<R: BinaryPeeker + ?Sized, E: ByteOrder>(reader: &mut R, range: Range<u64>, width: SlotWidth) -> Result<Vec<SlotGuess>, InterpreterError> {
+    let len = reader.total_len()?;
+    let end = range.end.min(len);
+    let size = width.bytes() as u64;
+    let mut slots = Vec::new();
+    let mut offset = range.start;
+    while offset + size <= end {
+        let bytes = reader.peek_bytes(offset, size as usize)?;
+        let mut candidates = Vec::new();
+
+        if bytes.iter().all(|&b| b == 0) {
+            candidates.push((Guess::Padding, 0.9));
+        } else {
+            let (value, float, float_score) = match width {
+                SlotWidth::Four => {
+                    let raw = E::read_u32(&bytes);
+                    let float = f32::from_bits(raw);
+                    (raw as i32 as i64, float as f64, float_score(float as f64, 23, raw.trailing_zeros()))
+                }
+                SlotWidth::Eight => {
+                    let raw = E::read_u64(&bytes);
+                    let float = f64::from_bits(raw);
+                    (raw as i64, float, float_score(float, 52, raw.trailing_zeros()))
+                }
+            };
+            let int_score = small_int_score(value);
+            if int_score > 0.0 {
+                candidates.push((Guess::SmallInt(value), int_score));
+            }
+            if float_score > 0.0 {
+                candidates.push((Guess::Float(float), float_score));
+            }
+            if value > 0 {
+                if let Some(guess) = offset_guess::<R, E>(reader, value as u64, width, len) {
+                    candidates.push(guess);
+                }
+            }
+            if let Some(text) = peek_text(reader, offset, Encoding::Utf8, len) {
+                candidates.push((Guess::Utf8String(text), 0.6));
+            }
+            if let Some(text) = peek_text(reader, offset, utf16::<E>(), len) {
+                candidates.push((Guess::Utf16String(text), 0.6));
+            }
+        }
+
+        candidates.sort_by(|a, b| b.1.total_cmp(&a.1));
+        slots.push(SlotGuess { offset, width, candidates });
+        offset += size;
+    }
+    Ok(slots)
+}
@@ -0,0 +1,110 @@
+use std::fmt::Write;
+use crate::recording::ReadRecord;
+
+const ROW: usize = 16;
+const PALETTE: [&str; 8] = ["#fbb4ae", "#b3cde3", "#ccebc5", "#decbe4", "#fed9a6", "#ffffcc", "#e5d8bd", "#fddaec"];
+
+fn describe(record: &ReadRecord) -> String {
+    let mut text = format!("{:#x} [{}]", record.offset, record.len);
+    if let Some(label) = &record.label {
+        write!(text, " {}", label).unwrap();
+    }
+    match (&record.type_name, &record.value) {
+        (Some(ty), Some(value)) => write!(text, ": {} = {}", ty, value).unwrap(),
+        (Some(ty), None) => write!(text, ": {} (failed)", ty).unwrap(),
+        _ => text.push_str(": bytes"),
+    }
+    text
+}
+
+/// Index of the record that last covered each byte of `data`.
+fn owners(len: usize, records: &[ReadRecord]) -> Vec<Option<usize>> {
+    let mut owners = vec![None; len];
+    for (i, record) in records.iter().enumerate() {
+        let start = (record.offset as usize).min(len);
+        let end = (record.end() as usize).min(len);
+        owners[start..end].fill(Some(i));
+    }
+    owners
+}
+
+fn printable(byte: u8) -> char {
+    if byte.is_ascii_graphic() || byte == b' ' { byte as char } else { '.' }
+}
+
+/// Renders `data` as a hex dump, listing the records that start on each row in the right margin.
+pub fn render_text(data: &[u8], records: &[ReadRecord]) -> String {
+    let mut out = String::new();
+    let mut sorted: Vec<&ReadRecord> = records.iter().collect();
+    sorted.sort_by_key(|r| r.offset);
+    let mut next = 0;
+    let blank = " ".repeat(10 + ROW * 3 + ROW + 2);
+
+    for (row, chunk) in data.chunks(ROW).enumerate() {
+        let start = (row * ROW) as u64;
+        write!(out, "{:08x}  ", start).unwrap();
+        for i in 0..ROW {
+            match chunk.get(i) {
+                Some(byte) => write!(out, "{:02x} ", byte).unwrap(),
+                None => out.push_str("   "),
+            }
+        }
+        out.push('|');
+        out.extend(chunk.iter().map(|&b| printable(b)));
+        out.extend(std::iter::repeat_n(' ', ROW - chunk.len()));
+        out.push('|');
+
+        let mut first = true;
+        while next < sorted.len() && sorted[next].offset < start + ROW as u64 {
+            if !first {
+                out.push('\n');
+                out.push_str(&blank);
+            }
+            write!(out, "  {}", describe(sorted[next])).unwrap();
+            first = false;
+            next += 1;
+        }
+        out.push('\n');
+    }
+    out
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// Renders `data` as a self-contained HTML page. Each record gets its own colour, its description is
+/// shown on hover and in the margin of the row it starts on.
+pub fn render_html(data: &[u8], records: &[ReadRecord]) -> String {
+    let owners = owners(data.len(), records);
+    let mut sorted: Vec<usize> = (0..records.len()).collect();
+    sorted.sort_by_key(|&r| records[r].offset);
+    let mut next = 0;
+    let mut out = String::new();
+    out.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>hex dump</title>\n<style>\n");
+    out.push_str("body { font-family: monospace; }\n.row { white-space: pre; }\n.u { color: #999; }\n.m { margin-left: 2em; }\n");
+    for (i, colour) in PALETTE.iter().enumerate() {
+        writeln!(out, ".c{} {{ background: {}; }}", i, colour).unwrap();
+    }
+    out.push_str("</style>\n</head>\n<body>\n");
+
+    for (row, chunk) in data.chunks(ROW).enumerate() {
+        let start = row * ROW;
+        write!(out, "<div class=\"row\">{:08x}  ", start).unwrap();
+        for (i, byte) in chunk.iter().enumerate() {
+            match owners[start + i] {
+                Some(r) => write!(out, "<span class=\"c{}\" title=\"{}\">{:02x}</span> ", r % PALETTE.len(), escape(&describe(&records[r])), byte).unwrap(),
+                None => write!(out, "<span class=\"u\">{:02x}</span> ", byte).unwrap(),
+            }
+        }
+        out.push_str(&"   ".repeat(ROW - chunk.len()));
+        while next < sorted.len() && records[sorted[next]].offset < (start + ROW) as u64 {
+            let r = sorted[next];
+            write!(out, "<span class=\"m c{}\">{}</span>", r % PALETTE.len(), escape(&describe(&records[r]))).unwrap();
+            next += 1;
+        }
+        out.push_str("</div>\n");
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
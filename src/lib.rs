@@ -1,15 +1,55 @@
 extern crate core;
 
+pub mod address;
+pub mod aob;
 pub mod binary_reader;
+pub mod binary_writer;
+pub mod bit_reader;
+pub mod bit_writer;
+pub mod coverage;
+pub mod elf;
+pub mod encoding;
+pub mod endian;
 pub mod error;
+pub mod guess;
+pub mod hex_dump;
 mod util;
+pub mod pe;
 pub mod pod;
+pub mod pointer_scan;
+#[cfg(target_os = "linux")]
+pub mod process;
+pub mod record_iter;
+pub mod recording;
+pub mod rtti;
+pub mod snapshot;
+pub mod strings;
+pub mod va_reader;
+pub mod value_scan;
 
 #[cfg(test)]
 mod tests {
-    use std::io::Cursor;
-    use byteorder::{BE, LE};
-    use crate::binary_reader::{BinaryPeeker, BinaryReader};
+    use std::io::{Cursor, Seek, SeekFrom};
+    use byteorder::{BE, LE, ReadBytesExt, WriteBytesExt};
+    use crate::address::{Address, AddressMap, Identity, Module, PointerExpr, PointerWidth};
+    use crate::aob::{Match, Pattern};
+    use crate::binary_reader::{BinaryPeeker, BinaryReader, PaddingMode};
+    use crate::error::InterpreterError;
+    use crate::binary_writer::{BinaryPoker, BinaryWriter};
+    use crate::bit_reader::{BitOrder, BitReader};
+    use crate::bit_writer::BitWriter;
+    use crate::elf::{Dynamic, Elf, Symbol, DT_NEEDED, DT_STRTAB, PT_DYNAMIC, SHT_DYNSYM, SHT_SYMTAB};
+    use crate::encoding::Encoding;
+    use crate::endian::Endian;
+    use crate::guess::{guess_fields, Guess, OffsetTarget, SlotWidth};
+    use crate::pe::{Export, Import, Pe, Relocation, RuntimeFunction};
+    use crate::pointer_scan::{PointerPath, PointerScanner};
+    use crate::recording::RecordingReader;
+    use crate::rtti::{demangle, Rtti, Vtable};
+    use crate::snapshot::{capture, Snapshot};
+    use crate::strings::{find_string_refs, FoundString, StringScanner};
+    use crate::va_reader::{Segment, VaReader};
+    use crate::value_scan::{FirstScan, NextScan, ValueScan};
 
     #[test]
     fn read_c_string() {
@@ -32,6 +72,7 @@ mod tests {
         let expected = hello_world.as_slice().read_fixed_cstr(4).unwrap();
 
         assert_eq!(expected, "Hell");
+        assert_eq!([0x48, 0x69].as_slice().read_fixed_cstr(4).unwrap(), "Hi\0\0");
     }
 
     #[test]
@@ -170,6 +211,1273 @@ mod tests {
     }
 
 
+    #[test]
+    fn read_encoded_strings() {
+        let utf16_be: Vec<u8> = vec![0x00, 0x48, 0x00, 0x69, 0x00, 0x00, 0xFF];
+        assert_eq!(utf16_be.as_slice().read_cstr_encoded(Encoding::Utf16BE).unwrap(), "Hi");
+
+        let latin1: Vec<u8> = vec![0x63, 0x61, 0x66, 0xE9, 0x00];
+        assert_eq!(latin1.as_slice().read_cstr_encoded(Encoding::Latin1).unwrap(), "caf\u{e9}");
+
+        let padded: Vec<u8> = vec![0x48, 0x00, 0x69, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01];
+        let mut c = Cursor::new(padded);
+        assert_eq!(c.read_fixed_cstr_encoded(4, Encoding::Utf16LE).unwrap(), "Hi");
+        assert_eq!(c.position(), 8);
+        assert_eq!(c.peek_fixed_cstr_encoded(0, 1, Encoding::Utf16LE).unwrap(), "H");
+        assert_eq!(c.position(), 8);
+        let io_kind = |r: Result<String, InterpreterError>| match r {
+            Err(InterpreterError::IoError(e)) => Some(e.kind()),
+            _ => None,
+        };
+        assert_eq!(io_kind(c.read_fixed_cstr_encoded(usize::MAX, Encoding::Utf16LE)), Some(std::io::ErrorKind::InvalidInput));
+        assert_eq!(io_kind(c.read_fixed_cstr_encoded(1 << 40, Encoding::Latin1)), Some(std::io::ErrorKind::UnexpectedEof));
+    }
+
+    #[test]
+    fn write_encoded_strings() {
+        let mut buf = Vec::new();
+        buf.write_cstr_encoded("Hi", Encoding::Utf16BE).unwrap();
+        buf.write_fixed_cstr_encoded("ab", 4, Encoding::Latin1).unwrap();
+        assert_eq!(buf, vec![0x00, 0x48, 0x00, 0x69, 0x00, 0x00, 0x61, 0x62, 0x00, 0x00]);
+
+        assert!(buf.write_fixed_cstr_encoded("abcde", 4, Encoding::Latin1).is_err());
+        assert!(matches!(buf.write_fixed_cstr_encoded("a", usize::MAX, Encoding::Utf16LE), Err(InterpreterError::IoError(e)) if e.kind() == std::io::ErrorKind::InvalidInput));
+        assert!(buf.write_cstr_encoded("\u{3042}", Encoding::Latin1).is_err());
+    }
+
+    #[cfg(feature = "code-pages")]
+    #[test]
+    fn shift_jis_round_trip() {
+        let mut buf = Vec::new();
+        buf.write_cstr_encoded("\u{3053}\u{3093}\u{306b}\u{3061}\u{306f}", Encoding::SHIFT_JIS).unwrap();
+        assert_eq!(&buf[..4], &[0x82, 0xB1, 0x82, 0xF1]);
+        let mut c = Cursor::new(buf);
+        assert_eq!(c.peek_cstr_encoded(0, Encoding::SHIFT_JIS).unwrap(), "\u{3053}\u{3093}\u{306b}\u{3061}\u{306f}");
+
+        let cp1252: Vec<u8> = vec![0x80, 0x00];
+        assert_eq!(cp1252.as_slice().read_cstr_encoded(Encoding::WINDOWS_1252).unwrap(), "\u{20ac}");
+    }
+
+
+    #[test]
+    fn pascal_strings() {
+        let mut buf = Vec::new();
+        buf.write_pstr::<u8, LE>("Hello", Encoding::Utf8).unwrap();
+        buf.write_pstr::<u16, BE>("Hi", Encoding::Utf16BE).unwrap();
+        assert_eq!(&buf[..6], &[0x05, 0x48, 0x65, 0x6c, 0x6c, 0x6f]);
+        assert_eq!(&buf[6..8], &[0x00, 0x02]);
+
+        let mut c = Cursor::new(buf);
+        assert_eq!(c.read_pstr::<u8, LE>(Encoding::Utf8).unwrap(), "Hello");
+        assert_eq!(c.peek_pstr::<u16, BE>(6, Encoding::Utf16BE).unwrap(), "Hi");
+        assert_eq!(c.position(), 6);
+
+        let huge: Vec<u8> = vec![0xFF, 0xFF, 0xFF, 0xFF];
+        assert!(matches!(huge.as_slice().read_pstr::<u32, LE>(Encoding::Utf8), Err(InterpreterError::AllocationTooLarge { .. })));
+        assert!(matches!(vec![0u8; 1].write_pstr::<u8, LE>(&"a".repeat(256), Encoding::Utf8), Err(InterpreterError::StringTooLong { .. })));
+    }
+
+    #[test]
+    fn dotnet_strings() {
+        let long = "a".repeat(200);
+        let mut buf = Vec::new();
+        buf.write_dotnet_string("Hello").unwrap();
+        buf.write_dotnet_string(&long).unwrap();
+        assert_eq!(buf[0], 5);
+        assert_eq!(&buf[6..8], &[0xC8, 0x01]);
+
+        let mut c = Cursor::new(buf);
+        assert_eq!(c.read_dotnet_string().unwrap(), "Hello");
+        assert_eq!(c.read_dotnet_string().unwrap(), long);
+        assert_eq!(c.peek_dotnet_string(0).unwrap(), "Hello");
+
+        let overlong: Vec<u8> = vec![0x80, 0x80, 0x80, 0x80, 0x80, 0x01];
+        assert!(matches!(overlong.as_slice().read_dotnet_string(), Err(InterpreterError::VarintOverflow(32))));
+    }
+
+
+    #[test]
+    fn leb128() {
+        let uleb: Vec<u8> = vec![0xE5, 0x8E, 0x26];
+        assert_eq!(uleb.as_slice().read_uleb128().unwrap(), 624485);
+        let sleb: Vec<u8> = vec![0xC0, 0xBB, 0x78];
+        assert_eq!(sleb.as_slice().read_sleb128().unwrap(), -123456);
+
+        let mut buf = Vec::new();
+        for value in [0, 1, 63, 64, -64, -65, i64::MAX, i64::MIN] {
+            buf.write_sleb128(value).unwrap();
+            buf.write_uleb128(value as u64).unwrap();
+            buf.write_varint_zigzag(value).unwrap();
+        }
+        buf.write_uleb128_u128(u128::MAX).unwrap();
+        buf.write_sleb128_i128(i128::MIN).unwrap();
+        let mut c = Cursor::new(buf);
+        for value in [0, 1, 63, 64, -64, -65, i64::MAX, i64::MIN] {
+            assert_eq!(c.read_sleb128().unwrap(), value);
+            assert_eq!(c.read_uleb128().unwrap(), value as u64);
+            assert_eq!(c.read_varint_zigzag().unwrap(), value);
+        }
+        assert_eq!(c.read_uleb128_u128().unwrap(), u128::MAX);
+        assert_eq!(c.read_sleb128_i128().unwrap(), i128::MIN);
+        assert_eq!(c.peek_uleb128(0).unwrap(), 0);
+    }
+
+    #[test]
+    fn leb128_rejects_overflow() {
+        let overflow: Vec<u8> = vec![0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x02];
+        assert!(matches!(overflow.as_slice().read_uleb128(), Err(InterpreterError::VarintOverflow(64))));
+        let overlong: Vec<u8> = vec![0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x00];
+        assert!(matches!(overlong.as_slice().read_uleb128(), Err(InterpreterError::VarintOverlong)));
+        let bad_sign: Vec<u8> = vec![0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x3F];
+        assert!(matches!(bad_sign.as_slice().read_sleb128(), Err(InterpreterError::VarintOverflow(64))));
+        let padded: Vec<u8> = vec![0x81, 0x80, 0x00];
+        assert_eq!(padded.as_slice().read_uleb128().unwrap(), 1);
+    }
+
+    #[test]
+    fn prefix_varint() {
+        let mut buf = Vec::new();
+        let values = [0, 127, 128, 16383, 16384, u64::MAX >> 8, u64::MAX];
+        for value in values {
+            buf.write_prefix_varint(value).unwrap();
+        }
+        assert_eq!(&buf[..1], &[0x01]);
+        let mut c = Cursor::new(buf);
+        for value in values {
+            assert_eq!(c.read_prefix_varint().unwrap(), value);
+        }
+        let non_canonical: Vec<u8> = vec![0x02, 0x00];
+        assert!(matches!(non_canonical.as_slice().read_prefix_varint(), Err(InterpreterError::VarintOverlong)));
+    }
+
+
+    #[test]
+    fn read_bits() {
+        let data: Vec<u8> = vec![0b1011_0010, 0b0111_1111, 0x34, 0x12];
+        let mut msb = BitReader::new(data.as_slice(), BitOrder::MsbFirst);
+        assert_eq!(msb.read_bits(3).unwrap(), 0b101);
+        assert!(msb.read_bool().unwrap());
+        assert_eq!(msb.read_signed_bits(6).unwrap(), 9);
+        assert_eq!(msb.read_signed_bits(4).unwrap(), -1);
+
+        let mut lsb = BitReader::new(data.as_slice(), BitOrder::LsbFirst);
+        assert_eq!(lsb.read_bits(3).unwrap(), 0b010);
+        assert_eq!(lsb.read_bits(7).unwrap(), 0b11_10110);
+        lsb.align();
+        assert_eq!(lsb.read_u16::<LE>().unwrap(), 0x1234);
+    }
+
+    #[test]
+    fn peek_and_seek_bits() {
+        let data: Vec<u8> = vec![0b1011_0010, 0b0111_1111, 0x34, 0x12];
+        let mut bits = BitReader::new(Cursor::new(data), BitOrder::MsbFirst);
+        bits.read_bits(5).unwrap();
+        assert_eq!(bits.peek_bits(6, 4).unwrap(), 0b1001);
+        assert_eq!(bits.bit_position().unwrap(), 5);
+        bits.seek_bits(12).unwrap();
+        assert_eq!(bits.read_bits(4).unwrap(), 0xF);
+        assert!(bits.is_aligned());
+        assert_eq!(bits.read_u8().unwrap(), 0x34);
+    }
+
+    #[test]
+    fn bit_round_trip() {
+        for order in [BitOrder::MsbFirst, BitOrder::LsbFirst] {
+            let mut writer = BitWriter::new(Vec::new(), order);
+            writer.write_bits(0b101, 3).unwrap();
+            writer.write_signed_bits(-5, 7).unwrap();
+            writer.write_bool(true).unwrap();
+            writer.write_bits(u64::MAX, 64).unwrap();
+            writer.align().unwrap();
+            writer.write_u16::<BE>(0xBEEF).unwrap();
+            let buf = writer.into_inner().unwrap();
+
+            let mut reader = BitReader::new(buf.as_slice(), order);
+            assert_eq!(reader.read_bits(3).unwrap(), 0b101);
+            assert_eq!(reader.read_signed_bits(7).unwrap(), -5);
+            assert!(reader.read_bool().unwrap());
+            assert_eq!(reader.read_bits(64).unwrap(), u64::MAX);
+            reader.align();
+            assert_eq!(reader.read_u16::<BE>().unwrap(), 0xBEEF);
+        }
+    }
+
+
+    #[test]
+    fn read_arrays() {
+        let data: Vec<u8> = vec![0x3F, 0x80, 0x00, 0x00, 0xC0, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x02];
+        let mut c = Cursor::new(data);
+        assert_eq!(c.peek_array::<u16, BE>(8, 2).unwrap(), vec![1, 2]);
+        assert_eq!(c.read_array::<f32, BE>(2).unwrap(), vec![1.0, -2.0]);
+
+        let mut pairs = [[0u16; 2]; 1];
+        c.read_into::<[u16; 2], LE>(&mut pairs).unwrap();
+        assert_eq!(pairs, [[0x0100, 0x0200]]);
+
+        let mut buf = Vec::new();
+        buf.write_array::<f32, BE>(&[1.0, -2.0]).unwrap();
+        assert_eq!(&buf[..], &c.get_ref()[..8]);
+
+        assert!(matches!(c.read_array::<u64, LE>(usize::MAX / 4), Err(InterpreterError::AllocationTooLarge { .. })));
+        // A count the stream cannot hold fails at its end instead of allocating every value first.
+        let eof = |e: &InterpreterError| matches!(e, InterpreterError::IoError(e) if e.kind() == std::io::ErrorKind::UnexpectedEof);
+        assert!(eof(&Cursor::new(vec![0u8; 8]).read_array::<u32, LE>(50_000_000).unwrap_err()));
+        assert!(eof(&[0xFF, 0xFF, 0xFF, 0x00, b'a'].as_slice().read_pstr::<u32, LE>(Encoding::Utf8).unwrap_err()));
+    }
+
+
+    #[test]
+    fn record_iterators() {
+        let data: Vec<u8> = vec![0x01, 0x00, 0x02, 0x00, 0x03, 0x00, 0x00, 0x00, 0x04, 0x00];
+        let mut c = Cursor::new(data);
+        let strided: Vec<u16> = c.strided_iter::<u16, LE>(0, 4, 3).collect::<Result<_, _>>().unwrap();
+        assert_eq!(strided, vec![1, 3, 4]);
+        assert_eq!(c.position(), 0);
+
+        let mut overflowing = c.strided_iter::<u16, LE>(u64::MAX - 1, u64::MAX / 2, 3);
+        assert!(matches!(overflowing.next(), Some(Err(InterpreterError::Element { index: 0, .. }))));
+        let mut overflowing = c.strided_iter::<u16, LE>(1, u64::MAX, 3);
+        assert!(matches!(overflowing.next(), Some(Ok(0x0200))));
+        assert!(matches!(overflowing.next(), Some(Err(InterpreterError::Element { index: 1, .. }))));
+        assert!(overflowing.next().is_none());
+
+        let records: Vec<u16> = c.read_until_sentinel::<u16, LE, _>(|r| *r == 0).collect::<Result<_, _>>().unwrap();
+        assert_eq!(records, vec![1, 2, 3]);
+        assert_eq!(c.position(), 8);
+
+        c.set_position(0);
+        let mut iter = c.read_iter::<[u16; 2], LE>(3);
+        assert_eq!(iter.next().unwrap().unwrap(), [1, 2]);
+        assert_eq!(iter.next().unwrap().unwrap(), [3, 0]);
+        assert!(matches!(iter.next(), Some(Err(InterpreterError::Element { index: 2, .. }))));
+        assert!(iter.next().is_none());
+    }
+
+
+    #[test]
+    fn magic() {
+        let data: Vec<u8> = vec![0x42, 0x4E, 0x44, 0x34, 0x01, 0x00, 0x00, 0x01];
+        let mut c = Cursor::new(data);
+        c.expect_magic(b"BND4").unwrap();
+        assert_eq!(c.expect_magic_endian(&[0x01, 0x00]).unwrap(), Endian::Little);
+        assert_eq!(c.peek_magic_endian(6, &[0x01, 0x00]).unwrap(), Endian::Big);
+        assert!(c.has_magic(0, b"BND4").unwrap());
+        assert!(!c.has_magic(6, b"BND4").unwrap());
+
+        match c.peek_magic(4, b"BND3") {
+            Err(InterpreterError::BadMagic { expected, found, offset }) => {
+                assert_eq!(expected, b"BND3");
+                assert_eq!(found, vec![0x01, 0x00, 0x00, 0x01]);
+                assert_eq!(offset, 4);
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert_eq!(c.position(), 6);
+    }
+
+
+    #[test]
+    fn alignment_and_padding() {
+        let data: Vec<u8> = vec![0x01, 0x00, 0x00, 0x00, 0x02, 0x00, 0xFF, 0x00, 0x03, 0xCC, 0xCC, 0xCC];
+        let mut c = Cursor::new(data);
+        c.skip(1).unwrap();
+        assert_eq!(c.align_to_zeros(4, PaddingMode::Strict).unwrap(), 4);
+        c.skip(1).unwrap();
+        match c.align_to_zeros(8, PaddingMode::Strict) {
+            Err(InterpreterError::BadPadding { offset, expected, found }) => {
+                assert_eq!((offset, expected, found), (6, 0, 0xFF));
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+        c.set_position(5);
+        c.align_to_zeros(8, PaddingMode::Permissive).unwrap();
+        assert_eq!(c.align_to(4).unwrap(), 8);
+        c.skip(1).unwrap();
+        c.expect_fill(3, 0xCC, PaddingMode::Strict).unwrap();
+        assert!(c.skip(1).is_err());
+
+        let mut out = Cursor::new(Vec::new());
+        out.write_u8(1).unwrap();
+        out.pad_to(4, 0xCC).unwrap();
+        out.pad_to(4, 0xCC).unwrap();
+        out.poke_bytes(1, &[0x00]).unwrap();
+        assert_eq!(out.into_inner(), vec![0x01, 0x00, 0xCC, 0xCC]);
+    }
+
+
+    #[test]
+    fn stream_length() {
+        let data: Vec<u8> = vec![0x48, 0x65, 0x6c, 0x6c, 0x6f, 0x2c, 0x20, 0x57, 0x6f, 0x72, 0x6c, 0x64, 0x21, 0x0, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06];
+        let mut c = Cursor::new(data);
+        c.set_position(7);
+        assert_eq!(c.total_len().unwrap(), 20);
+        assert_eq!(c.remaining().unwrap(), 13);
+        assert_eq!(c.peek_rest(14).unwrap(), vec![0x01, 0x02, 0x03, 0x04, 0x05, 0x06]);
+        assert_eq!(c.position(), 7);
+        assert_eq!(c.read_to_end_bytes().unwrap().len(), 13);
+        assert!(c.is_eof().unwrap());
+
+        let truncated: Vec<u8> = vec![0x10, 0x00, 0x00, 0x00, 0x41];
+        let mut c = Cursor::new(truncated);
+        match c.peek_pstr::<u32, LE>(0, Encoding::Utf8) {
+            Err(InterpreterError::Truncated { offset, needed, remaining }) => assert_eq!((offset, needed, remaining), (4, 16, 1)),
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert!(matches!(c.read_array_checked::<u32, LE>(2), Err(InterpreterError::Truncated { .. })));
+    }
+
+
+    #[test]
+    fn recording_reader() {
+        let data: Vec<u8> = vec![0x42, 0x4E, 0x44, 0x34, 0x02, 0x00, 0x00, 0x00, 0x48, 0x69, 0x00, 0xFF];
+        let mut r = RecordingReader::new(Cursor::new(data)).unwrap();
+        r.expect_magic(b"BND4").unwrap();
+        let count = r.read_as::<u32, LE>("count").unwrap();
+        assert_eq!(count, 2);
+        let name = r.record("name", |r| r.read_cstr()).unwrap();
+        assert_eq!(name, "Hi");
+        assert_eq!(r.peek_as::<u8, LE>(11, "tail").unwrap(), 0xFF);
+
+        let records = r.records();
+        assert_eq!(records.len(), 4);
+        assert_eq!((records[0].offset, records[0].len, records[0].type_name), (0, 4, None));
+        assert_eq!(records[1].label.as_deref(), Some("count"));
+        assert_eq!(records[1].value.as_deref(), Some("2"));
+        assert_eq!((records[2].offset, records[2].len), (8, 3));
+        assert_eq!(records[2].value.as_deref(), Some("\"Hi\""));
+        assert_eq!(records[3].offset, 11);
+
+        let text = r.render_text().unwrap();
+        assert!(text.starts_with("00000000  42 4e 44 34 02 00 00 00 48 69 00 ff"));
+        assert!(text.contains("0x4 [4] count: u32 = 2"));
+        let html = r.render_html().unwrap();
+        assert!(html.contains("title=\"0x8 [3] name: alloc::string::String = &quot;Hi&quot;\">48</span>"));
+    }
+
+
+    #[test]
+    fn recording_reader_typed_reads() {
+        let data: Vec<u8> = vec![0x01, 0x00, 0x02, 0x00, 0x00, 0x00, 0x41, 0x00, 0xAA, 0xBB];
+        let mut r = RecordingReader::new(Cursor::new(data)).unwrap();
+        assert_eq!(r.read_u16::<LE>().unwrap(), 1);
+        assert_eq!(r.read_u32::<LE>().unwrap(), 2);
+        assert_eq!(r.read_cstr().unwrap(), "A");
+        assert_eq!(r.peek_u8(0).unwrap(), 1);
+        fn read_two<R: std::io::Read>(r: &mut R) {
+            let mut byte = [0u8; 1];
+            r.read_exact(&mut byte).unwrap();
+            r.read_exact(&mut byte).unwrap();
+        }
+        read_two(&mut r);
+
+        let records = r.records();
+        assert_eq!(records.len(), 6);
+        assert_eq!((records[0].offset, records[0].len, records[0].type_name, records[0].value.as_deref()), (0, 2, Some("u16"), Some("1")));
+        assert_eq!((records[1].offset, records[1].len, records[1].type_name, records[1].value.as_deref()), (2, 4, Some("u32"), Some("2")));
+        assert_eq!((records[2].offset, records[2].len, records[2].value.as_deref()), (6, 2, Some("\"A\"")));
+        assert_eq!((records[3].offset, records[3].len, records[3].type_name), (0, 1, Some("u8")));
+        assert_eq!((records[4].offset, records[4].len, records[4].type_name), (8, 1, None));
+        assert_eq!((records[5].offset, records[5].len, records[5].type_name), (9, 1, None));
+        assert!(records.iter().all(|r| r.label.is_none()));
+    }
+
+
+    #[test]
+    fn coverage_report() {
+        let data: Vec<u8> = vec![0x00; 24];
+        let mut r = RecordingReader::new(Cursor::new(data)).unwrap();
+        r.read_as::<u32, LE>("a").unwrap();
+        r.skip(4).unwrap();
+        r.seek(SeekFrom::Start(12)).unwrap();
+        r.read_as::<u64, LE>("b").unwrap();
+        r.peek_as::<f32, LE>(16, "c").unwrap();
+        r.peek_as::<u16, LE>(12, "d").unwrap();
+
+        let coverage = r.coverage().unwrap();
+        assert_eq!(coverage.unread, vec![8..12, 20..24]);
+        assert_eq!(coverage.read, 16);
+        assert_eq!(coverage.conflicts.len(), 2);
+        assert_eq!(coverage.conflicts[0].range, 12..14);
+        assert_eq!(coverage.conflicts[0].types, vec!["u16", "u64"]);
+        assert_eq!(coverage.conflicts[1].range, 16..20);
+        assert_eq!(coverage.conflicts[1].types, vec!["f32", "u64"]);
+        assert_eq!(coverage.overlaps, coverage.conflicts);
+
+        let summary = coverage.to_string();
+        assert!(summary.starts_with("16 of 24 bytes read (66.7%)"));
+        assert!(summary.contains("unread   0x00000008..0x0000000c (4 bytes)"));
+        assert!(summary.contains("conflict 0x00000010..0x00000014 read as f32, u64"));
+    }
+
+
+    #[test]
+    fn coverage_same_type_overlap() {
+        let data: Vec<u8> = vec![0x00; 12];
+        let mut r = RecordingReader::new(Cursor::new(data)).unwrap();
+        r.read_as::<u32, LE>("a").unwrap();
+        r.peek_as::<u32, LE>(2, "b").unwrap();
+        r.seek(SeekFrom::Start(8)).unwrap();
+        std::io::Read::read_exact(&mut r, &mut [0u8; 4]).unwrap();
+        r.seek(SeekFrom::Start(10)).unwrap();
+        std::io::Read::read_exact(&mut r, &mut [0u8; 2]).unwrap();
+
+        let coverage = r.coverage().unwrap();
+        assert_eq!(coverage.unread, vec![6..8]);
+        assert!(coverage.conflicts.is_empty());
+        assert_eq!(coverage.overlaps.len(), 2);
+        assert_eq!(coverage.overlaps[0].range, 2..4);
+        assert_eq!(coverage.overlaps[0].types, vec!["u32"]);
+        assert_eq!(coverage.overlaps[1].range, 10..12);
+        assert_eq!(coverage.overlaps[1].types, vec!["bytes"]);
+        assert!(coverage.to_string().contains("overlap  0x00000002..0x00000004 read as u32"));
+    }
+
+
+    #[test]
+    fn guess_unknown_fields() {
+        let mut data = Vec::new();
+        data.write_u32::<LE>(0).unwrap();
+        data.write_u32::<LE>(7).unwrap();
+        data.write_f32::<LE>(1.5).unwrap();
+        data.write_u32::<LE>(24).unwrap();
+        data.write_cstr_encoded("ABC", Encoding::Utf16LE).unwrap();
+        data.write_cstr("name").unwrap();
+        let mut c = Cursor::new(data);
+
+        let slots = guess_fields::<_, LE>(&mut c, 0..20, SlotWidth::Four).unwrap();
+        assert_eq!(slots.len(), 5);
+        assert_eq!(slots[0].best(), Some(&Guess::Padding));
+        assert_eq!(slots[1].best(), Some(&Guess::SmallInt(7)));
+        assert_eq!(slots[2].best(), Some(&Guess::Float(1.5)));
+        assert_eq!(slots[3].best(), Some(&Guess::Offset { target: 24, points_to: OffsetTarget::String("name".to_string()) }));
+        assert!(slots[4].candidates.contains(&(Guess::Utf16String("ABC".to_string()), 0.6)));
+        assert_eq!(c.position(), 0);
+
+        // Big endian data holds big endian UTF-16.
+        let mut data = Vec::new();
+        data.write_u32::<BE>(8).unwrap();
+        data.write_u32::<BE>(0).unwrap();
+        data.write_cstr_encoded("ABC", Encoding::Utf16BE).unwrap();
+        let slots = guess_fields::<_, BE>(&mut Cursor::new(data), 0..16, SlotWidth::Four).unwrap();
+        assert_eq!(slots[0].best(), Some(&Guess::Offset { target: 8, points_to: OffsetTarget::String("ABC".to_string()) }));
+        assert!(slots[2].candidates.contains(&(Guess::Utf16String("ABC".to_string()), 0.6)));
+    }
+
+
+    #[test]
+    fn extract_strings() {
+        let mut data = Vec::new();
+        data.write_u32::<LE>(16).unwrap();
+        data.write_u32::<LE>(23).unwrap();
+        data.write_u32::<LE>(0xFFFF_FFFF).unwrap();
+        data.write_u32::<LE>(0).unwrap();
+        data.write_cstr("player").unwrap();
+        data.write_u8(0xFF).unwrap();
+        data.write_cstr_encoded("Wide", Encoding::Utf16LE).unwrap();
+        data.write_cstr_encoded("Big", Encoding::Utf16BE).unwrap();
+        data.write_cstr("\u{e9}t\u{e9}").unwrap();
+        let mut c = Cursor::new(data);
+
+        let found: Vec<FoundString> = StringScanner::new(&mut c, 3).collect::<Result<_, _>>().unwrap();
+        assert_eq!(found, vec![
+            FoundString { offset: 16, encoding: Encoding::Utf8, text: "player".to_string() },
+            FoundString { offset: 24, encoding: Encoding::Utf16LE, text: "Wide".to_string() },
+            FoundString { offset: 34, encoding: Encoding::Utf16BE, text: "Big".to_string() },
+            FoundString { offset: 42, encoding: Encoding::Utf8, text: "\u{e9}t\u{e9}".to_string() },
+        ]);
+
+        let xrefs = find_string_refs::<_, LE>(&mut c, &found, SlotWidth::Four, 0).unwrap();
+        assert_eq!(xrefs.len(), 1);
+        assert_eq!((xrefs[0].index, xrefs[0].referenced_from.clone()), (0, vec![0]));
+        assert_eq!(c.position(), 0);
+    }
+
+    #[test]
+    fn string_scanner_orders_across_chunks() {
+        // A UTF-16LE run from 0xFFF0 that crosses the first 64 KiB chunk, with short UTF-8 strings inside it.
+        let mut data = vec![0u8; 0xFFF0];
+        for _ in 0..8 {
+            data.extend_from_slice(&[0x01, 0x4E, 0x61, 0x80]);
+        }
+        data.resize(0x10100, 0);
+        let mut c = Cursor::new(data);
+
+        let found: Vec<FoundString> = StringScanner::with_encodings(&mut c, 2, &[Encoding::Utf8, Encoding::Utf16LE]).collect::<Result<_, _>>().unwrap();
+        assert_eq!(found.len(), 9);
+        assert_eq!((found[0].offset, found[0].encoding), (0xFFF0, Encoding::Utf16LE));
+        assert_eq!(found[0].text.chars().count(), 16);
+        assert!(found.windows(2).all(|w| w[0].offset <= w[1].offset));
+        assert!(found[1..].iter().all(|s| s.encoding == Encoding::Utf8 && s.text == "Na"));
+    }
+
+
+    #[test]
+    fn aob_patterns() {
+        let pattern: Pattern = "48 8B 05 | ?? ?? ?? ?? 4? 85 C?".parse().unwrap();
+        assert_eq!(pattern.len(), 10);
+        assert_eq!(pattern.capture_offset(), 3);
+        assert_eq!(pattern.to_string(), "48 8B 05 |?? ?? ?? ?? 4? 85 C?");
+        assert!("48 8G".parse::<Pattern>().is_err());
+        assert!("| 48 | 8B".parse::<Pattern>().is_err());
+        let trailing: Pattern = "48 8B |".parse().unwrap();
+        assert_eq!(trailing.capture_offset(), 2);
+        assert_eq!(trailing.to_string(), "48 8B |");
+        assert_eq!(trailing.to_string().parse::<Pattern>().unwrap().capture_offset(), 2);
+        let masked = Pattern::new(vec![0x48, 0xFF], vec![0xFF, 0xF0], 0).unwrap();
+        assert_eq!(masked.to_string(), "48 F?");
+        assert_eq!(masked.find(&[0x90, 0x48, 0xF3]), Some(Match { offset: 1, capture: 1 }));
+
+        let mut code = vec![0x90; 40];
+        code[5..15].copy_from_slice(&[0x48, 0x8B, 0x05, 0x10, 0x00, 0x00, 0x00, 0x48, 0x85, 0xC0]);
+        code[28..38].copy_from_slice(&[0x48, 0x8B, 0x05, 0xF0, 0xFF, 0xFF, 0xFF, 0x4C, 0x85, 0xC9]);
+        let matches: Vec<Match> = pattern.find_iter(&code).collect();
+        assert_eq!(matches, vec![Match { offset: 5, capture: 8 }, Match { offset: 28, capture: 31 }]);
+
+        let mut c = Cursor::new(code);
+        let scanned: Vec<Match> = pattern.scan(&mut c).with_chunk_size(8).collect::<Result<_, _>>().unwrap();
+        assert_eq!(scanned, matches);
+        assert_eq!(c.peek_i32::<LE>(scanned[1].capture).unwrap(), -16);
+    }
+
+
+    struct ImageBase(u64);
+
+    impl AddressMap for ImageBase {
+        fn to_offset(&self, va: u64) -> Option<u64> {
+            va.checked_sub(self.0).filter(|&o| o < 0x100)
+        }
+
+        fn to_va(&self, offset: u64) -> Option<u64> {
+            Some(self.0 + offset)
+        }
+    }
+
+    #[test]
+    fn resolve_relative_addresses() {
+        let mut data = vec![0u8; 0x40];
+        // mov rax, [rip+0x20] at 0x10, call -0x15 at 0x17, add eax, 1 at 0x1C
+        data[0x10..0x17].copy_from_slice(&[0x48, 0x8B, 0x05, 0x20, 0x00, 0x00, 0x00]);
+        data[0x17..0x1C].copy_from_slice(&[0xE8, 0xEB, 0xFF, 0xFF, 0xFF]);
+        data[0x1C..0x1F].copy_from_slice(&[0x83, 0xC0, 0x01]);
+        let mut cursor = Cursor::new(data);
+        cursor.seek(SeekFrom::Start(3)).unwrap();
+
+        assert_eq!(cursor.resolve_rel32(0x10, 7, &Identity).unwrap(), Address { va: 0x37, offset: Some(0x37) });
+        assert_eq!(cursor.follow_call(0x17, &Identity).unwrap().va, 0x07);
+        let mapped = cursor.resolve_rel32(0x10, 7, &ImageBase(0x1400_0000)).unwrap();
+        assert_eq!(mapped, Address { va: 0x1400_0037, offset: Some(0x37) });
+        assert!(matches!(cursor.follow_call(0x1C, &Identity), Err(InterpreterError::UnexpectedOpcode { offset: 0x1C, opcode: 0x83 })));
+        assert_eq!(cursor.stream_position().unwrap(), 3);
+
+        // Lengths that cannot hold a displacement, and addresses that overflow, are rejected up front.
+        for insn_len in 0..4 {
+            assert!(matches!(cursor.resolve_rel32(0x10, insn_len, &Identity), Err(InterpreterError::IoError(e)) if e.kind() == std::io::ErrorKind::InvalidInput));
+        }
+        assert!(matches!(cursor.resolve_rel32(u64::MAX - 2, 7, &Identity), Err(InterpreterError::Malformed { what: "rel32 instruction", offset: 0xFFFF_FFFF_FFFF_FFFD })));
+        assert!(matches!(cursor.resolve_rel32(0x10, 7, &ImageBase(u64::MAX - 0x12)), Err(InterpreterError::Malformed { what: "rel32 instruction", offset: 0x10 })));
+    }
+
+    #[test]
+    fn pointer_paths() {
+        let path: PointerExpr = "[[game.exe+10]+0x8]+0x40".parse().unwrap();
+        assert_eq!(path, PointerExpr::chain(PointerExpr::Symbol("game.exe".to_string()), &[0x10, 0x8, 0x40]));
+        assert_eq!(path.to_string(), "[[\"game.exe\"+0x10]+0x8]+0x40");
+        assert_eq!(path.to_string().parse::<PointerExpr>().unwrap(), path);
+        assert!("[base+0x10".parse::<PointerExpr>().is_err());
+        assert!("base+".parse::<PointerExpr>().is_err());
+
+        let mut data = vec![0u8; 0x80];
+        data[0x10..0x18].copy_from_slice(&0x1400_0030u64.to_le_bytes());
+        data[0x38..0x40].copy_from_slice(&0x1400_0050u64.to_le_bytes());
+        let mut cursor = Cursor::new(data);
+        let map = ImageBase(0x1400_0000);
+        let symbols = |name: &str| (name == "game.exe").then_some(0x1400_0000);
+        let target = cursor.resolve_pointer_path::<LE, _, _>(&path, PointerWidth::U64, &map, &symbols).unwrap();
+        assert_eq!(target, Address { va: 0x1400_0090, offset: Some(0x90) });
+
+        let unknown: PointerExpr = "[other.dll+8]".parse().unwrap();
+        assert!(matches!(cursor.resolve_pointer_path::<LE, _, _>(&unknown, PointerWidth::U64, &map, &symbols), Err(InterpreterError::UnknownSymbol(_))));
+        let wild: PointerExpr = "[[game.exe+10]+0x1000]".parse().unwrap();
+        assert!(matches!(cursor.resolve_pointer_path::<LE, _, _>(&wild, PointerWidth::U64, &map, &symbols), Err(InterpreterError::UnmappedAddress(0x1400_1030))));
+    }
+
+    #[test]
+    fn va_reader_translates_addresses() {
+        let mut data = vec![0u8; 0x30];
+        data[0x10..0x18].copy_from_slice(&0x40_2004u64.to_le_bytes());
+        data[0x20..0x24].copy_from_slice(&0xDEADBEEFu32.to_le_bytes());
+        let segments = vec![
+            Segment::new(0x40_2000, 0x20, 0x20, 0x10),
+            Segment::new(0x40_1000, 0x10, 0x10, 0x10),
+        ];
+        let mut reader = VaReader::new(Cursor::new(data), segments);
+        assert_eq!(reader.stream_position().unwrap(), 0x40_1000);
+        assert_eq!(reader.segments()[0].va_start, 0x40_1000);
+
+        assert_eq!(reader.peek_u32::<LE>(0x40_2000).unwrap(), 0xDEADBEEF);
+        // The second segment is only partly backed by the file; the rest reads as zeroes.
+        assert_eq!(reader.peek_bytes(0x40_200C, 8).unwrap(), vec![0; 8]);
+        assert_eq!(reader.file_offset(0x40_2008), Some(0x28));
+        assert_eq!(reader.file_offset(0x40_2018), None);
+
+        let err = reader.peek_u32::<LE>(0x40_3000).unwrap_err();
+        assert!(matches!(err.get_ref().and_then(|e| e.downcast_ref()), Some(InterpreterError::UnmappedAddress(0x40_3000))));
+        assert!(reader.peek_bytes(0x40_101C, 8).is_err());
+        assert_eq!(reader.total_len().unwrap(), 0x40_2020);
+
+        // Pointers stored in the file can be followed directly.
+        let path: PointerExpr = "[401000]".parse().unwrap();
+        let target = reader.resolve_pointer_path::<LE, _, _>(&path, PointerWidth::U64, &Identity, &|_: &str| None).unwrap();
+        assert_eq!(reader.peek_u16::<LE>(target.va).unwrap(), 0);
+        let in_file = reader.segments().to_offset(target.va);
+        assert_eq!(in_file, Some(0x24));
+        assert_eq!(reader.segments().to_va(0x24), Some(0x40_2004));
+    }
+
+    /// Spawns `sleep`, waits until its image is mapped and runs `test` with its pid and the image's first region.
+    #[cfg(target_os = "linux")]
+    fn with_sleeping_child<F>(test: F)
+    where
+        F: FnOnce(u32, crate::process::MemoryRegion) -> Result<(), InterpreterError>,
+    {
+        use crate::process::parse_maps;
+
+        let mut child = std::process::Command::new("sleep").arg("30").spawn().unwrap();
+        let pid = child.id();
+        let result = (|| {
+            // The child may not have finished exec'ing `sleep` yet, and the mem file is tied to the address
+            // space it was opened on, so wait for the image before opening it.
+            let mut image = None;
+            for _ in 0..200 {
+                image = parse_maps(&std::fs::read_to_string(format!("/proc/{}/maps", pid))?)?.into_iter()
+                    .find(|r| r.offset == 0 && r.protection.read && r.path.as_deref().is_some_and(|p| p.ends_with("sleep")));
+                if image.is_some() {
+                    break;
+                }
+                std::thread::sleep(std::time::Duration::from_millis(10));
+            }
+            test(pid, image.expect("sleep is mapped"))
+        })();
+        child.kill().unwrap();
+        child.wait().unwrap();
+        result.unwrap();
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn read_child_process_memory() {
+        use crate::process::{parse_maps, ProcessMemory};
+
+        let maps = "00400000-00452000 r-xp 00000000 08:02 173521      /usr/bin/dbus-daemon\n\
+                    7fff5d1e0000-7fff5d201000 rw-s 00001000 00:00 0 \n";
+        let regions = parse_maps(maps).unwrap();
+        assert_eq!(regions[0].range, 0x400000..0x452000);
+        assert!(regions[0].protection.execute && regions[0].protection.private && !regions[0].protection.write);
+        assert_eq!(regions[0].path.as_deref(), Some("/usr/bin/dbus-daemon"));
+        assert_eq!((regions[1].offset, regions[1].path.clone(), regions[1].protection.private), (0x1000, None, false));
+        assert!(parse_maps("garbage").is_err());
+
+        with_sleeping_child(|pid, image| {
+            let mut memory = ProcessMemory::open(pid)?;
+            assert!(memory.regions()?.contains(&image));
+            memory.seek(SeekFrom::Start(image.range.start))?;
+            memory.expect_magic(b"\x7FELF")?;
+            assert_eq!(memory.stream_position()?, image.range.start + 4);
+            assert_eq!(memory.peek_u8(image.range.start + 1)?, b'E');
+
+            let mut buf = [0u8; 4];
+            memory.read_exact_at(image.range.start, &mut buf)?;
+            assert_eq!(&buf, b"\x7FELF");
+            let err = memory.peek_u32::<LE>(0).unwrap_err();
+            assert!(matches!(err.get_ref().and_then(|e| e.downcast_ref()), Some(InterpreterError::UnreadableMemory(0))));
+            Ok(())
+        });
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn patch_child_process_memory() {
+        use crate::process::ProcessMemory;
+
+        with_sleeping_child(|pid, image| {
+            // The ELF identification padding is unused once the image is loaded.
+            let pad = image.range.start + 9;
+            let mut memory = ProcessMemory::open_writable(pid)?;
+            let original = memory.peek_bytes(pad, 7)?;
+
+            memory.poke_u32::<LE>(pad, 0x11223344)?;
+            assert_eq!(memory.peek_u32::<LE>(pad)?, 0x11223344);
+            memory.poke_bytes(pad, &original)?;
+
+            {
+                let patch = memory.patch(pad, &[0xAA, 0xBB])?;
+                assert_eq!(patch.original(), &original[..2]);
+                assert_eq!(memory.peek_bytes(pad, 2)?, vec![0xAA, 0xBB]);
+            }
+            assert_eq!(memory.peek_bytes(pad, 7)?, original);
+
+            let set = memory.patch_all(&[(pad, &[1, 2, 3]), (pad + 1, &[9])])?;
+            assert_eq!(set.patches().len(), 2);
+            assert_eq!(memory.peek_bytes(pad, 3)?, vec![1, 9, 3]);
+            set.restore()?;
+            assert_eq!(memory.peek_bytes(pad, 7)?, original);
+
+            // A failing patch restores the ones applied before it.
+            let failed = memory.patch_all(&[(pad, &[5, 5]), (0, &[0])]);
+            assert!(matches!(failed, Err(InterpreterError::UnreadableMemory(0))));
+            assert_eq!(memory.peek_bytes(pad, 7)?, original);
+
+            memory.patch(pad, &[7])?.keep();
+            assert_eq!(memory.peek_u8(pad)?, 7);
+
+            let frozen = memory.freeze(pad, &[0x42], std::time::Duration::from_millis(1))?;
+            assert_eq!(frozen.address(), pad);
+            memory.poke_u8(pad, 0)?;
+            let mut refreshed = false;
+            for _ in 0..200 {
+                std::thread::sleep(std::time::Duration::from_millis(5));
+                if memory.peek_u8(pad)? == 0x42 {
+                    refreshed = true;
+                    break;
+                }
+            }
+            assert!(refreshed);
+            drop(frozen);
+            memory.poke_u8(pad, 0)?;
+            std::thread::sleep(std::time::Duration::from_millis(20));
+            assert_eq!(memory.peek_u8(pad)?, 0);
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn scan_selected_ranges() {
+        let mut data = vec![0u8; 0x100];
+        for at in [0x10, 0x40, 0x7E, 0xC0] {
+            data[at..at + 2].copy_from_slice(&[0x12, 0x34]);
+        }
+        let mut cursor = Cursor::new(data);
+        let pattern: Pattern = "12 34".parse().unwrap();
+        let results: Vec<_> = pattern.scan_ranges(&mut cursor, [0x30..0x80, 0xB0..0x200, 0x00..0x11])
+            .with_chunk_size(8)
+            .collect();
+        // 0xB0..0x200 runs past the end of the stream, which ends that range but not the scan.
+        assert_eq!(results.iter().filter(|r| r.is_err()).count(), 1);
+        let found: Vec<u64> = results.into_iter().filter_map(|m| m.ok().map(|m| m.offset)).collect();
+        assert_eq!(found, vec![0x40, 0x7E, 0xC0]);
+        let found: Vec<u64> = pattern.scan_ranges(&mut cursor, [0x30..0x80, 0x00..0x12])
+            .map(|m| m.unwrap().offset)
+            .collect();
+        assert_eq!(found, vec![0x40, 0x7E, 0x10]);
+        assert_eq!(Pattern::from_value::<u16, BE>(0x1234).unwrap().find(&cursor.get_ref()[0x3F..]).unwrap().offset, 1);
+        assert!(matches!(Pattern::from_value::<[u32; 0], LE>([]), Err(InterpreterError::BadPattern(_))));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn scan_child_process_regions() {
+        use crate::process::{parse_maps, ProcessMemory, RegionFilter};
+
+        let maps = "00400000-00401000 r--p 00000000 08:02 1 /home/user/Game/GAME.EXE\n\
+                    00401000-00402000 r-xp 00001000 08:02 1 /home/user/Game/GAME.EXE\n\
+                    00500000-00501000 rw-s 00000000 00:00 0 [heap]\n";
+        let regions = parse_maps(maps).unwrap();
+        let code = RegionFilter::new().module("game.exe").executable(true);
+        assert_eq!(regions.iter().filter(|r| code.matches(r)).count(), 1);
+        assert_eq!(regions.iter().filter(|r| RegionFilter::new().private(false).matches(r)).count(), 1);
+        assert_eq!(regions[2].module_name(), None);
+
+        with_sleeping_child(|pid, image| {
+            let mut memory = ProcessMemory::open(pid)?;
+            let filter = RegionFilter::new().module("sleep");
+            let selected = memory.regions_matching(&filter)?;
+            assert!(selected.contains(&image) && selected.iter().all(|r| r.module_name() == Some("sleep")));
+
+            let pattern: Pattern = "7F 45 4C 46 ?? 01".parse().unwrap();
+            let found: Vec<Match> = memory.scan(&pattern, &filter)?.collect::<Result<_, _>>()?;
+            assert_eq!(found.first().map(|m| m.offset), Some(image.range.start));
+
+            let found = memory.scan_value::<u32, LE>(0x464C457F, &RegionFilter::new().writable(false))?;
+            assert!(found.contains(&image.range.start));
+
+            let modules = memory.modules()?;
+            let module = Module::named(&modules, "sleep").expect("sleep is a module");
+            assert!(module.range.start == image.range.start && module.range.end >= image.range.end);
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn value_scan_narrows_candidates() {
+        let mut data = vec![0u8; 0x40];
+        for (i, value) in [100u32, 7, 100, 250, 100].iter().enumerate() {
+            data[i * 8..i * 8 + 4].copy_from_slice(&value.to_be_bytes());
+        }
+        let mut cursor = Cursor::new(data);
+        let mut scan = ValueScan::<u32, BE>::first_scan(&mut cursor, Some(0..0x30), 4, FirstScan::Exact(100)).unwrap();
+        assert_eq!(scan.iter().collect::<Vec<_>>(), vec![(0x00, 100), (0x10, 100), (0x20, 100)]);
+
+        cursor.poke_u32::<BE>(0x10, 105).unwrap();
+        cursor.poke_u32::<BE>(0x20, 90).unwrap();
+        let mut changed = scan.clone();
+        changed.next_scan(&mut cursor, NextScan::Changed).unwrap();
+        assert_eq!(changed.len(), 2);
+        scan.next_scan(&mut cursor, NextScan::IncreasedBy(5)).unwrap();
+        assert_eq!(scan.iter().collect::<Vec<_>>(), vec![(0x10, 105)]);
+
+        // An unknown initial value keeps every aligned slot of every range.
+        let mut unknown = ValueScan::<u32, BE>::first_scan(&mut cursor, [0x2E..0x40, 0x00..0x08], 2, FirstScan::Unknown).unwrap();
+        assert_eq!(unknown.len(), 8 + 3);
+        cursor.poke_u32::<BE>(0x30, 1).unwrap();
+        unknown.next_scan(&mut cursor, NextScan::Unchanged).unwrap();
+        assert_eq!(unknown.len(), 11 - 2);
+        unknown.next_scan(&mut cursor, NextScan::Between(1, 200)).unwrap();
+        assert_eq!(unknown.iter().collect::<Vec<_>>(), vec![(0x00, 100)]);
+        let unaligned = ValueScan::<u16, LE>::first_scan(&mut cursor, Some(0..0x40), 1, FirstScan::Unknown).unwrap();
+        assert_eq!(unaligned.len(), 0x3F);
+
+        let mut floats = Cursor::new([1.5f32, -2.0, 1.75, f32::NAN].iter().flat_map(|f| f.to_le_bytes()).collect::<Vec<u8>>());
+        let mut scan = ValueScan::<f32, LE>::first_scan(&mut floats, Some(0..16), 4, FirstScan::Unknown).unwrap();
+        scan.next_scan(&mut floats, NextScan::Unchanged).unwrap();
+        assert_eq!(scan.len(), 4);
+        floats.poke_f32::<LE>(0, 1.0).unwrap();
+        scan.next_scan(&mut floats, NextScan::Decreased).unwrap();
+        assert_eq!(scan.iter().map(|(a, _)| a).collect::<Vec<_>>(), vec![0]);
+        assert!(ValueScan::<f32, LE>::first_scan(&mut floats, Some(0..16), 4, FirstScan::Between(1.6, 2.0)).unwrap().iter().eq([(8, 1.75)]));
+    }
+
+    #[test]
+    fn pointer_scan_finds_static_paths() {
+        // game.exe is mapped at 0x10000 and followed by the heap.
+        let snapshot = |object: u64| {
+            let mut data = Cursor::new(vec![0u8; 0x400]);
+            data.poke_u64::<LE>(0x40, 0x10200).unwrap();
+            data.poke_u64::<LE>(0x218, object).unwrap();
+            data.poke_u64::<LE>(0x80, 0x10300).unwrap();
+            VaReader::new(data, vec![Segment::new(0x10000, 0x400, 0, 0x400)])
+        };
+        let mut memory = snapshot(0x10300);
+        let modules = vec![Module { name: "game.exe".to_string(), range: 0x10000..0x10100 }];
+        let ranges = vec![0x10000..0x10100, 0x10100..0x10400];
+
+        let scanner = PointerScanner::new(PointerWidth::U64).with_max_depth(3).with_max_offset(0x100);
+        let paths = scanner.scan::<_, LE>(&mut memory, &ranges, &modules, 0x10340).unwrap();
+        assert_eq!(paths, vec![
+            PointerPath { module: "game.exe".to_string(), offsets: vec![0x80, 0x40] },
+            PointerPath { module: "game.exe".to_string(), offsets: vec![0x40, 0x18, 0x40] },
+        ]);
+        assert_eq!(paths[1].to_string(), "[[\"game.exe\"+0x40]+0x18]+0x40");
+        assert_eq!(scanner.clone().with_max_depth(1).scan::<_, LE>(&mut memory, &ranges, &modules, 0x10340).unwrap().len(), 1);
+        let saved: Vec<String> = paths.iter().map(|p| p.to_string()).collect();
+
+        // In a later snapshot the object has moved; only the path through the heap object survives.
+        let mut later = snapshot(0x10380);
+        let stable: Vec<PointerPath> = saved.iter()
+            .map(|s| s.parse::<PointerPath>().unwrap())
+            .filter(|p| p.points_to::<_, LE>(&mut later, PointerWidth::U64, &modules, 0x103C0))
+            .collect();
+        assert_eq!(stable, vec![paths[1].clone()]);
+        assert!("[0x10]+8".parse::<PointerPath>().is_err());
+        let renamed = [Module { name: "GAME.EXE".to_string(), range: 0x10000..0x10100 }];
+        assert_eq!(paths[0].resolve::<_, LE>(&mut later, PointerWidth::U64, &renamed).unwrap(), 0x10340);
+    }
+
+
+    #[test]
+    fn pointer_scan_skips_cycles() {
+        // Two heap objects point at each other, and game.exe points at the first.
+        let mut data = Cursor::new(vec![0u8; 0x400]);
+        data.poke_u64::<LE>(0x40, 0x10200).unwrap();
+        data.poke_u64::<LE>(0x208, 0x10300).unwrap();
+        data.poke_u64::<LE>(0x308, 0x10200).unwrap();
+        let mut memory = VaReader::new(data, vec![Segment::new(0x10000, 0x400, 0, 0x400)]);
+        let modules = vec![Module { name: "game.exe".to_string(), range: 0x10000..0x10100 }];
+        let ranges = vec![0x10000..0x10100, 0x10100..0x10400];
+
+        let scanner = PointerScanner::new(PointerWidth::U64).with_max_depth(16).with_max_offset(0x100);
+        let paths = scanner.scan::<_, LE>(&mut memory, &ranges, &modules, 0x10310).unwrap();
+        assert_eq!(paths, vec![PointerPath { module: "game.exe".to_string(), offsets: vec![0x40, 0x8, 0x10] }]);
+    }
+
+    #[test]
+    fn snapshot_capture_and_diff() {
+        let memory = |health: u32, speed: f32| {
+            let mut data = Cursor::new(vec![0u8; 0x40]);
+            data.poke_u32::<LE>(0x08, health).unwrap();
+            data.poke_f32::<LE>(0x2C, speed).unwrap();
+            data.poke_bytes(0x30, b"ELF!").unwrap();
+            VaReader::new(data, vec![Segment::new(0x7000, 0x20, 0, 0x20), Segment::new(0x9000, 0x20, 0x20, 0x10)])
+        };
+        let regions = || vec![(0x7000..0x7020, Some("/opt/game/game.exe".to_string())), (0x9000..0x9020, Some("[heap]".to_string())), (0xA000..0xA010, None)];
+
+        let mut file = Cursor::new(Vec::new());
+        let table = capture(&mut memory(100, 1.5), regions(), &mut file).unwrap();
+        assert_eq!(table.len(), 2);
+        assert_eq!(&file.get_ref()[..8], b"BISNAP\r\n");
+
+        file.set_position(0);
+        let mut before = Snapshot::open(file).unwrap();
+        assert_eq!(before.regions(), &table[..]);
+        assert_eq!(before.ranges(), vec![0x7000..0x7020, 0x9000..0x9020]);
+        assert_eq!(before.modules(), vec![Module { name: "game.exe".to_string(), range: 0x7000..0x7020 }]);
+        assert_eq!(before.reader().peek_u32::<LE>(0x7008).unwrap(), 100);
+        assert_eq!(before.reader().peek_bytes(0x9010, 4).unwrap(), vec![0; 4]);
+        assert!(before.reader().peek_u8(0xA000).is_err());
+
+        let mut file = Cursor::new(Vec::new());
+        capture(&mut memory(75, 1.5), regions(), &mut file).unwrap();
+        file.set_position(0);
+        let mut after = Snapshot::open(file).unwrap();
+        let changes = before.diff(&mut after, 4).unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].range, 0x7008..0x700C);
+        assert_eq!(changes[0].values::<u32, LE>(), vec![(0x7008, 100, 75)]);
+
+        let mut file = Cursor::new(Vec::new());
+        capture(&mut memory(75, 3.0), regions(), &mut file).unwrap();
+        file.set_position(0);
+        let mut faster = Snapshot::open(file).unwrap();
+        let changes = after.diff(&mut faster, 8).unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].range, 0x9008..0x9010);
+        assert_eq!(changes[0].values::<f32, LE>(), vec![(0x900C, 1.5, 3.0)]);
+
+        assert!(matches!(Snapshot::open(Cursor::new(b"not a snapshot".to_vec())), Err(InterpreterError::BadMagic { .. })));
+
+        // A snapshot embedded after other data is opened from where it was written.
+        let mut file = Cursor::new(b"header".to_vec());
+        file.set_position(6);
+        capture(&mut memory(100, 1.5), regions(), &mut file).unwrap();
+        file.set_position(6);
+        let mut embedded = Snapshot::open(file).unwrap();
+        assert_eq!(embedded.regions(), &table[..]);
+        assert_eq!(embedded.reader().peek_u32::<LE>(0x7008).unwrap(), 100);
+
+        let mut file = embedded.into_reader().into_inner();
+        file.poke_u32::<LE>(6 + 8, 2).unwrap();
+        file.set_position(6);
+        assert!(matches!(Snapshot::open(file), Err(InterpreterError::UnsupportedVersion { what: "snapshot", found: 2, expected: 1 })));
+    }
+
+    /// A minimal x64 (or x86) image with a code section and an `.rdata` section holding an import, an
+    /// export, a forwarded export, base relocations and two unwind entries.
+    fn pe_fixture(plus: bool) -> Vec<u8> {
+        let mut pe = Cursor::new(vec![0u8; 0x700]);
+        pe.poke_bytes(0, b"MZ").unwrap();
+        pe.poke_u32::<LE>(0x3C, 0x40).unwrap();
+        pe.poke_bytes(0x40, b"PE\0\0").unwrap();
+        let (optional_size, directories) = if plus { (0xF0, 0x58 + 112) } else { (0xE0, 0x58 + 96) };
+        pe.poke_u16::<LE>(0x44, if plus { 0x8664 } else { 0x14C }).unwrap();
+        pe.poke_u16::<LE>(0x46, 2).unwrap();
+        pe.poke_u16::<LE>(0x54, optional_size).unwrap();
+        pe.poke_u16::<LE>(0x58, if plus { 0x20B } else { 0x10B }).unwrap();
+        pe.poke_u32::<LE>(0x58 + 16, 0x1000).unwrap();
+        if plus {
+            pe.poke_u64::<LE>(0x58 + 24, 0x1_4000_0000).unwrap();
+        } else {
+            pe.poke_u32::<LE>(0x58 + 28, 0x40_0000).unwrap();
+        }
+        pe.poke_u32::<LE>(0x58 + 32, 0x1000).unwrap();
+        pe.poke_u32::<LE>(0x58 + 36, 0x200).unwrap();
+        pe.poke_u32::<LE>(0x58 + 56, 0x3000).unwrap();
+        pe.poke_u32::<LE>(0x58 + 60, 0x200).unwrap();
+        pe.poke_u16::<LE>(0x58 + 68, 3).unwrap();
+        pe.poke_u32::<LE>(directories - 4, 16).unwrap();
+        for (index, rva, size) in [(0, 0x2100, 0x80), (1, 0x2000, 40), (3, 0x2280, 24), (5, 0x2200, 16)] {
+            pe.poke_u32::<LE>(directories + index * 8, rva).unwrap();
+            pe.poke_u32::<LE>(directories + index * 8 + 4, size).unwrap();
+        }
+        let sections = 0x58 + optional_size as u64;
+        for (i, (name, virtual_size, rva, raw_size, raw)) in [(b".text\0\0\0", 0x100u32, 0x1000u32, 0x200u32, 0x200u32), (b".rdata\0\0", 0x400, 0x2000, 0x300, 0x400)].iter().enumerate() {
+            let header = sections + i as u64 * 40;
+            pe.poke_bytes(header, *name).unwrap();
+            for (field, value) in [*virtual_size, *rva, *raw_size, *raw].into_iter().enumerate() {
+                pe.poke_u32::<LE>(header + 8 + field as u64 * 4, value).unwrap();
+            }
+        }
+        pe.poke_bytes(0x200, &[0xC3]).unwrap();
+
+        // .rdata starts at file offset 0x400, RVA 0x2000.
+        let at = |rva: u64| rva - 0x2000 + 0x400;
+        for (field, value) in [0x2040, 0, 0, 0x2080, 0x2060].iter().enumerate() {
+            pe.poke_u32::<LE>(at(0x2000) + field as u64 * 4, *value).unwrap();
+        }
+        for table in [0x2040, 0x2060] {
+            if plus {
+                pe.poke_u64::<LE>(at(table), 0x2090).unwrap();
+                pe.poke_u64::<LE>(at(table) + 8, 0x8000_0000_0000_0005).unwrap();
+            } else {
+                pe.poke_u32::<LE>(at(table), 0x2090).unwrap();
+                pe.poke_u32::<LE>(at(table) + 4, 0x8000_0005).unwrap();
+            }
+        }
+        pe.poke_bytes(at(0x2080), b"KERNEL32.dll\0").unwrap();
+        pe.poke_u16::<LE>(at(0x2090), 0x12).unwrap();
+        pe.poke_bytes(at(0x2092), b"GetTickCount\0").unwrap();
+
+        for (field, value) in [0x2150, 1, 2, 2, 0x2130, 0x2138, 0x2140].iter().enumerate() {
+            pe.poke_u32::<LE>(at(0x2100) + 12 + field as u64 * 4, *value).unwrap();
+        }
+        pe.poke_u32::<LE>(at(0x2130), 0x1010).unwrap();
+        pe.poke_u32::<LE>(at(0x2134), 0x2160).unwrap();
+        pe.poke_u32::<LE>(at(0x2138), 0x2170).unwrap();
+        pe.poke_u32::<LE>(at(0x213C), 0x2174).unwrap();
+        pe.poke_u16::<LE>(at(0x2140), 1).unwrap();
+        pe.poke_u16::<LE>(at(0x2142), 0).unwrap();
+        pe.poke_bytes(at(0x2150), b"game.exe\0").unwrap();
+        pe.poke_bytes(at(0x2160), b"NTDLL.Foo\0").unwrap();
+        pe.poke_bytes(at(0x2170), b"Fwd\0Run\0").unwrap();
+
+        for (i, value) in [0x1000u32, 16].iter().enumerate() {
+            pe.poke_u32::<LE>(at(0x2200) + i as u64 * 4, *value).unwrap();
+        }
+        for (i, entry) in [0xA010u16, 0xA018, 0, 0].iter().enumerate() {
+            pe.poke_u16::<LE>(at(0x2208) + i as u64 * 2, *entry).unwrap();
+        }
+        for (i, value) in [0x1000u32, 0x1010, 0x2300, 0x1010, 0x1030, 0x2308].iter().enumerate() {
+            pe.poke_u32::<LE>(at(0x2280) + i as u64 * 4, *value).unwrap();
+        }
+        pe.into_inner()
+    }
+
+    #[test]
+    fn parse_pe_images() {
+        let mut file = Cursor::new(pe_fixture(true));
+        let pe = Pe::parse(&mut file).unwrap();
+        assert!(pe.is_pe32_plus());
+        assert_eq!(pe.pointer_width(), PointerWidth::U64);
+        assert_eq!((pe.file_header.machine, pe.image_base(), pe.optional_header.subsystem), (0x8664, 0x1_4000_0000, 3));
+        assert_eq!(pe.sections.iter().map(|s| s.name.as_str()).collect::<Vec<_>>(), vec![".text", ".rdata"]);
+        assert_eq!(pe.section_containing(0x2010).unwrap().name, ".rdata");
+        assert_eq!(pe.directory(1).unwrap().size, 40);
+        assert_eq!(pe.directory(2), None);
+
+        let imports = pe.imports(&mut file).unwrap();
+        assert_eq!(imports.len(), 1);
+        assert_eq!(imports[0].name, "KERNEL32.dll");
+        assert_eq!(imports[0].functions, vec![
+            Import { name: Some("GetTickCount".to_string()), hint: 0x12, ordinal: None, iat_rva: 0x2060 },
+            Import { name: None, hint: 0, ordinal: Some(5), iat_rva: 0x2068 },
+        ]);
+
+        let exports = pe.exports(&mut file).unwrap().unwrap();
+        assert_eq!((exports.dll_name.as_str(), exports.ordinal_base), ("game.exe", 1));
+        assert_eq!(exports.functions, vec![
+            Export { ordinal: 1, rva: 0x1010, name: Some("Run".to_string()), forwarder: None },
+            Export { ordinal: 2, rva: 0x2160, name: Some("Fwd".to_string()), forwarder: Some("NTDLL.Foo".to_string()) },
+        ]);
+        assert_eq!(pe.relocations(&mut file).unwrap(), vec![Relocation { rva: 0x1010, kind: 10 }, Relocation { rva: 0x1018, kind: 10 }]);
+        assert_eq!(pe.exception_functions(&mut file).unwrap()[1], RuntimeFunction { begin: 0x1010, end: 0x1030, unwind_info: 0x2308 });
+
+        // The section table maps the file by virtual address, zero filling past each section's raw data.
+        let mut image = pe.va_reader(file);
+        assert_eq!(image.peek_cstr(0x1_4000_2080).unwrap(), "KERNEL32.dll");
+        assert_eq!(image.peek_u8(0x1_4000_1000).unwrap(), 0xC3);
+        assert_eq!(image.peek_u64::<LE>(0x1_4000_2380).unwrap(), 0);
+        assert!(matches!(image.peek_u8(0x1_4000_1100).map_err(InterpreterError::from), Err(InterpreterError::UnmappedAddress(0x1_4000_1100))));
+        assert!(matches!(image.peek_array::<u8, LE>(0x1_4000_1100, 1), Err(InterpreterError::UnmappedAddress(0x1_4000_1100))));
+
+        let mut file = Cursor::new(pe_fixture(false));
+        let pe = Pe::parse(&mut file).unwrap();
+        assert_eq!((pe.pointer_width(), pe.image_base()), (PointerWidth::U32, 0x40_0000));
+        let imports = pe.imports(&mut file).unwrap();
+        assert_eq!(imports[0].functions[1], Import { name: None, hint: 0, ordinal: Some(5), iat_rva: 0x2064 });
+        assert_eq!(pe.exports(&mut file).unwrap().unwrap().functions.len(), 2);
+
+        let mut bad = pe_fixture(true);
+        bad[0x58] = 0x0B;
+        bad[0x59] = 0x03;
+        assert!(matches!(Pe::parse(&mut Cursor::new(bad)), Err(InterpreterError::Malformed { what: "optional header magic", offset: 0x58 })));
+        assert!(matches!(Pe::parse(&mut Cursor::new(vec![0x7F, b'E', b'L', b'F'])), Err(InterpreterError::BadMagic { offset: 0, .. })));
+
+        // Export ordinals and relocation targets that overflow an RVA are rejected, not wrapped.
+        let mut bad = Cursor::new(pe_fixture(true));
+        bad.poke_u32::<LE>(0x510, u32::MAX).unwrap();
+        let pe = Pe::parse(&mut bad).unwrap();
+        assert!(matches!(pe.exports(&mut bad), Err(InterpreterError::Malformed { what: "export ordinal base", offset: 0x2110 })));
+        bad.poke_u32::<LE>(0x600, 0xFFFF_FFF8).unwrap();
+        assert!(matches!(pe.relocations(&mut bad), Err(InterpreterError::Malformed { what: "relocation entry", offset: 0x2200 })));
+    }
+
+    #[test]
+    fn runtime_endian_reads() {
+        let mut c = Cursor::new(vec![0x12, 0x34, 0x56, 0x78, 0x9A, 0xBC, 0xDE, 0xF0]);
+        assert_eq!(c.read_u16_endian(Endian::Big).unwrap(), 0x1234);
+        assert_eq!(c.read_u16_endian(Endian::Little).unwrap(), 0x7856);
+        assert_eq!(c.read_i32_endian(Endian::Big).unwrap(), 0x9ABCDEF0u32 as i32);
+        assert_eq!(c.peek_u32_endian(0, Endian::Little).unwrap(), 0x78563412);
+        assert_eq!(c.peek_pointer_endian(0, PointerWidth::U64, Endian::Big).unwrap(), 0x123456789ABCDEF0);
+        c.set_position(4);
+        assert_eq!(c.read_pointer_endian(PointerWidth::U32, Endian::Little).unwrap(), 0xF0DEBC9A);
+    }
+
+    /// A big endian ELF32 executable with one loaded segment, a dynamic segment needing `libc.so.6`,
+    /// and a symbol table holding `main`.
+    fn elf32_be_fixture() -> Vec<u8> {
+        let mut elf = Cursor::new(vec![0u8; 0x200]);
+        elf.poke_bytes(0, &[0x7F, b'E', b'L', b'F', 1, 2, 1, 0]).unwrap();
+        for (at, value) in [(16, 2), (18, 8), (40, 52), (42, 32), (44, 2), (46, 40), (48, 4), (50, 1)] {
+            elf.poke_u16::<BE>(at, value).unwrap();
+        }
+        for (at, value) in [(20, 1), (24, 0x40_0100), (28, 0x34), (32, 0x100)] {
+            elf.poke_u32::<BE>(at, value).unwrap();
+        }
+        let program_headers = [
+            [1, 0, 0x40_0000, 0x40_0000, 0x200, 0x300, 5, 0x1000],
+            [2, 0xD0, 0x40_00D0, 0x40_00D0, 0x18, 0x18, 6, 4],
+        ];
+        for (i, header) in program_headers.iter().enumerate() {
+            for (field, value) in header.iter().enumerate() {
+                elf.poke_u32::<BE>(0x34 + i as u64 * 32 + field as u64 * 4, *value).unwrap();
+            }
+        }
+        elf.poke_bytes(0x80, b"\0.shstrtab\0.strtab\0.symtab\0").unwrap();
+        elf.poke_bytes(0xA0, b"\0libc.so.6\0main\0").unwrap();
+        for (field, value) in [11, 0x40_0100, 0x20].iter().enumerate() {
+            elf.poke_u32::<BE>(0xC0 + field as u64 * 4, *value).unwrap();
+        }
+        elf.poke_bytes(0xCC, &[0x12, 0]).unwrap();
+        elf.poke_u16::<BE>(0xCE, 1).unwrap();
+        for (field, value) in [1, 1, 5, 0x40_00A0].iter().enumerate() {
+            elf.poke_u32::<BE>(0xD0 + field as u64 * 4, *value).unwrap();
+        }
+        let sections = [
+            [1, 3, 0, 0, 0x80, 27, 0, 0, 1, 0],
+            [11, 3, 0, 0, 0xA0, 16, 0, 0, 1, 0],
+            [19, 2, 0, 0, 0xB0, 32, 2, 1, 4, 16],
+        ];
+        for (i, header) in sections.iter().enumerate() {
+            for (field, value) in header.iter().enumerate() {
+                elf.poke_u32::<BE>(0x100 + (i as u64 + 1) * 40 + field as u64 * 4, *value).unwrap();
+            }
+        }
+        elf.into_inner()
+    }
+
+    #[test]
+    fn parse_elf32_big_endian() {
+        let mut file = Cursor::new(elf32_be_fixture());
+        let elf = Elf::parse(&mut file).unwrap();
+        assert_eq!((elf.pointer_width(), elf.endian()), (PointerWidth::U32, Endian::Big));
+        assert_eq!((elf.header.machine, elf.header.entry, elf.header.section_header_count), (8, 0x40_0100, 4));
+        assert_eq!(elf.sections.iter().map(|s| s.name.as_str()).collect::<Vec<_>>(), vec!["", ".shstrtab", ".strtab", ".symtab"]);
+        assert_eq!(elf.program_headers[0].mem_size, 0x300);
+
+        let symbols = elf.symbols(&mut file, SHT_SYMTAB).unwrap();
+        assert_eq!(symbols.len(), 2);
+        assert_eq!(symbols[1], Symbol { name: "main".to_string(), value: 0x40_0100, size: 0x20, info: 0x12, other: 0, section_index: 1 });
+        assert_eq!((symbols[1].bind(), symbols[1].kind()), (1, 2));
+        assert!(elf.symbols(&mut file, SHT_DYNSYM).unwrap().is_empty());
+
+        assert_eq!(elf.dynamic(&mut file).unwrap(), vec![Dynamic { tag: DT_NEEDED, value: 1 }, Dynamic { tag: DT_STRTAB, value: 0x40_00A0 }]);
+        assert_eq!(elf.needed(&mut file).unwrap(), vec!["libc.so.6"]);
+
+        let mut image = elf.va_reader(file);
+        assert_eq!(image.peek_u32_endian(0x40_00C4, Endian::Big).unwrap(), 0x40_0100);
+        assert_eq!(image.peek_u8(0x40_0250).unwrap(), 0);
+        assert!(matches!(image.peek_u8(0x40_0300).map_err(InterpreterError::from), Err(InterpreterError::UnmappedAddress(0x40_0300))));
+
+        let mut bad = elf32_be_fixture();
+        bad[4] = 3;
+        assert!(matches!(Elf::parse(&mut Cursor::new(bad)), Err(InterpreterError::Malformed { what: "ELF class", offset: 4 })));
+        let mut bad = elf32_be_fixture();
+        bad[49] = 0x40;
+        assert!(matches!(Elf::parse(&mut Cursor::new(bad)), Err(InterpreterError::Malformed { what: "section header table", offset: 0x100 })));
+        assert!(matches!(Elf::parse(&mut Cursor::new(b"MZ\0\0".to_vec())), Err(InterpreterError::BadMagic { offset: 0, .. })));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn parse_elf_bin_true() {
+        let mut file = std::fs::File::open("/bin/true").unwrap();
+        let elf = Elf::parse(&mut file).unwrap();
+        assert_eq!(elf.endian(), Endian::native());
+        assert_eq!(elf.pointer_width().bytes(), size_of::<usize>());
+        assert!(!elf.segments().is_empty());
+
+        let imported = elf.symbols(&mut file, SHT_DYNSYM).unwrap();
+        assert!(imported.iter().any(|s| !s.is_defined() && !s.name.is_empty()));
+        assert!(elf.needed(&mut file).unwrap().iter().any(|library| library.starts_with("libc")));
+        if elf.pointer_width() == PointerWidth::U64 {
+            // A string table address that overflows when the name offset is added is rejected.
+            let mut bad = Cursor::new(std::fs::read("/bin/true").unwrap());
+            let index = elf.dynamic(&mut bad).unwrap().iter().position(|d| d.tag == DT_STRTAB).unwrap();
+            bad.poke_bytes(elf.program_header(PT_DYNAMIC).unwrap().offset + index as u64 * 16 + 8, &[0xFF; 8]).unwrap();
+            assert!(matches!(elf.needed(&mut bad), Err(InterpreterError::Malformed { what: "DT_NEEDED string offset", offset: u64::MAX })));
+        }
+
+        let text = elf.section_named(".text").unwrap().clone();
+        let expected = file.peek_bytes(text.offset, 16).unwrap();
+        let mut image = elf.va_reader(&mut file);
+        assert_eq!(image.peek_bytes(text.addr, 16).unwrap(), expected);
+    }
+
+    #[test]
+    fn demangle_rtti_names() {
+        assert_eq!(demangle(".?AVPlayer@@").unwrap(), "Player");
+        assert_eq!(demangle(".?AUPoint@math@game@@").unwrap(), "game::math::Point");
+        assert_eq!(demangle(".?AV?$vector@HV?$allocator@H@std@@@std@@").unwrap(), "std::vector<int,std::allocator<int>>");
+        assert_eq!(demangle(".?AV?$pair@VItem@@V1@@std@@").unwrap(), "std::pair<Item,Item>");
+        assert_eq!(demangle(".?AV?$Array@PEBD$0BA@@@").unwrap(), "Array<const char*,16>");
+        assert_eq!(demangle(".?AV?$Holder@_N@@").unwrap(), "Holder<bool>");
+        assert_eq!(demangle(".?AVPlayer@"), None);
+        assert_eq!(demangle(".?AV?A0x1234@@"), None);
+        assert_eq!(demangle("Player"), None);
+    }
+
+    #[test]
+    fn find_rtti_classes() {
+        // An x64 image at 0x140000000 where Holder<int> derives from game::Derived, which derives from Base.
+        // Holder<int> has a second vtable for a subobject at offset 0x10.
+        const BASE: u64 = 0x1_4000_0000;
+        let mut image = Cursor::new(vec![0u8; 0x500]);
+        image.poke_bytes(0x110, b".?AVBase@@\0").unwrap();
+        image.poke_bytes(0x150, b".?AVDerived@game@@\0").unwrap();
+        image.poke_bytes(0x190, b".?AV?$Holder@H@@\0").unwrap();
+        let descriptors = [(0x200, 0x100, 0), (0x220, 0x140, 1), (0x240, 0x180, 2)];
+        for (at, type_descriptor, contained) in descriptors {
+            for (field, value) in [type_descriptor, contained, 0, u32::MAX, 0, 0x40].iter().enumerate() {
+                image.poke_u32::<LE>(at + field as u64 * 4, *value).unwrap();
+            }
+        }
+        for (at, value) in [(0x280, 0x200), (0x290, 0x220), (0x294, 0x200), (0x2A0, 0x240), (0x2A4, 0x220), (0x2A8, 0x200)] {
+            image.poke_u32::<LE>(at, value).unwrap();
+        }
+        for (at, count, array) in [(0x2C0, 1, 0x280), (0x2D0, 2, 0x290), (0x2E0, 3, 0x2A0)] {
+            image.poke_u32::<LE>(at + 8, count).unwrap();
+            image.poke_u32::<LE>(at + 12, array).unwrap();
+        }
+        let locators = [(0x300, 0, 0x100, 0x2C0), (0x320, 0, 0x140, 0x2D0), (0x340, 0, 0x180, 0x2E0), (0x360, 0x10, 0x180, 0x2E0)];
+        for (at, offset, type_descriptor, hierarchy) in locators {
+            for (field, value) in [1, offset, 0, type_descriptor, hierarchy, at as u32].iter().enumerate() {
+                image.poke_u32::<LE>(at + field as u64 * 4, *value).unwrap();
+            }
+        }
+        for (slot, locator) in [(0x400, 0x300), (0x420, 0x320), (0x440, 0x340), (0x460, 0x360)] {
+            image.poke_u64::<LE>(slot, BASE + locator).unwrap();
+            image.poke_u64::<LE>(slot + 8, BASE + 0x1000).unwrap();
+        }
+        // A pointer to a type descriptor, which is not a locator.
+        image.poke_u64::<LE>(0x480, BASE + 0x100).unwrap();
+
+        let mut reader = VaReader::new(image, vec![Segment::new(BASE, 0x500, 0, 0x500)]);
+        let rtti = Rtti::new(BASE, PointerWidth::U64);
+        let classes = rtti.find_classes(&mut reader, &[BASE + 0x300..BASE + 0x500, BASE..BASE + 0x300]).unwrap();
+        assert_eq!(classes.iter().map(|c| c.name.as_str()).collect::<Vec<_>>(), vec!["Base", "game::Derived", "Holder<int>"]);
+        assert_eq!(classes[1].decorated, ".?AVDerived@game@@");
+        assert_eq!(classes[1].direct_bases().iter().map(|b| b.name.as_str()).collect::<Vec<_>>(), vec!["Base"]);
+        assert_eq!(classes[2].inheritance_chain(), vec!["Holder<int>", "game::Derived", "Base"]);
+        assert_eq!(classes[2].bases[0].pdisp, -1);
+        assert_eq!(classes[2].vtables, vec![
+            Vtable { address: BASE + 0x448, locator: BASE + 0x340, offset: 0 },
+            Vtable { address: BASE + 0x468, locator: BASE + 0x360, offset: 0x10 },
+        ]);
+
+        let locator = rtti.locator_for_vtable(&mut reader, BASE + 0x428).unwrap();
+        assert_eq!((locator.type_descriptor, locator.class_hierarchy), (BASE + 0x140, BASE + 0x2D0));
+        assert!(matches!(rtti.locator_for_vtable(&mut reader, BASE + 0x488), Err(InterpreterError::Malformed { what: "complete object locator", .. })));
+    }
+
     // old test code for old version of crate
     // #[test]
     // fn read_byte() {
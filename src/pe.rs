@@ -0,0 +1,356 @@
+use std::io::{Read, Seek, SeekFrom};
+use byteorder::LittleEndian as LE;
+use crate::address::PointerWidth;
+use crate::binary_reader::{BinaryPeeker, BinaryReader};
+use crate::encoding::Encoding;
+use crate::error::InterpreterError;
+use crate::va_reader::{Segment, VaReader};
+
+pub const DIRECTORY_EXPORT: usize = 0;
+pub const DIRECTORY_IMPORT: usize = 1;
+pub const DIRECTORY_EXCEPTION: usize = 3;
+pub const DIRECTORY_BASERELOC: usize = 5;
+
+/// Windows refuses images with more sections than this.
+const MAX_SECTIONS: u16 = 96;
+const MAX_DIRECTORIES: u32 = 16;
+/// Upper bound on entries walked in a zero terminated table, so a corrupt file cannot loop forever.
+const MAX_TABLE_ENTRIES: usize = 1 << 20;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileHeader {
+    pub machine: u16,
+    pub number_of_sections: u16,
+    pub time_date_stamp: u32,
+    pub size_of_optional_header: u16,
+    pub characteristics: u16,
+}
+
+/// The fields of the PE32 and PE32+ optional headers that differ only in width are widened to `u64`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OptionalHeader {
+    /// `0x10B` for PE32, `0x20B` for PE32+.
+    pub magic: u16,
+    pub address_of_entry_point: u32,
+    pub image_base: u64,
+    pub section_alignment: u32,
+    pub file_alignment: u32,
+    pub size_of_image: u32,
+    pub size_of_headers: u32,
+    pub subsystem: u16,
+    pub dll_characteristics: u16,
+    pub data_directories: Vec<DataDirectory>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DataDirectory {
+    pub virtual_address: u32,
+    pub size: u32,
+}
+
+impl DataDirectory {
+    pub fn contains(&self, rva: u32) -> bool {
+        (self.virtual_address..self.virtual_address.saturating_add(self.size)).contains(&rva)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Section {
+    pub name: String,
+    pub virtual_size: u32,
+    pub virtual_address: u32,
+    pub size_of_raw_data: u32,
+    pub pointer_to_raw_data: u32,
+    pub characteristics: u32,
+}
+
+impl Section {
+    /// Size in memory. Some linkers leave `virtual_size` zero and only fill in the raw size.
+    pub fn mapped_size(&self) -> u32 {
+        match self.virtual_size {
+            0 => self.size_of_raw_data,
+            size => size,
+        }
+    }
+}
+
+/// A function imported by name or by ordinal.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Import {
+    pub name: Option<String>,
+    pub hint: u16,
+    pub ordinal: Option<u16>,
+    /// RVA of the import address table slot the loader fills in.
+    pub iat_rva: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ImportedDll {
+    pub name: String,
+    pub functions: Vec<Import>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Export {
+    pub ordinal: u32,
+    pub rva: u32,
+    pub name: Option<String>,
+    /// `OTHER.Function` when the export forwards to another DLL.
+    pub forwarder: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Exports {
+    pub dll_name: String,
+    pub ordinal_base: u32,
+    pub functions: Vec<Export>,
+}
+
+/// A base relocation: the loader adjusts the value at `rva` when the image is not loaded at its preferred base.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Relocation {
+    pub rva: u32,
+    /// `IMAGE_REL_BASED_*`, e.g. 3 for `HIGHLOW` and 10 for `DIR64`.
+    pub kind: u8,
+}
+
+/// An x64 `RUNTIME_FUNCTION` from the exception directory.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RuntimeFunction {
+    pub begin: u32,
+    pub end: u32,
+    pub unwind_info: u32,
+}
+
+/// The headers and section table of a PE image. Reading the tables they point at takes the file again,
+/// through the methods that accept a reader.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Pe {
+    pub nt_offset: u32,
+    pub file_header: FileHeader,
+    pub optional_header: OptionalHeader,
+    pub sections: Vec<Section>,
+}
+
+impl Pe {
+    /// Parses the headers of the image at the start of `reader`. The cursor is left where it was.
+    pub fn parse<R: BinaryPeeker + ?Sized>(reader: &mut R) -> Result<Pe, InterpreterError> {
+        let start = reader.stream_position()?;
+        let pe = Pe::parse_headers(reader);
+        reader.seek(SeekFrom::Start(start))?;
+        pe
+    }
+
+    fn parse_headers<R: BinaryPeeker + ?Sized>(reader: &mut R) -> Result<Pe, InterpreterError> {
+        reader.peek_magic(0, b"MZ")?;
+        let nt_offset = reader.peek_u32::<LE>(0x3C)?;
+        reader.seek(SeekFrom::Start(nt_offset as u64))?;
+        reader.expect_magic(b"PE\0\0")?;
+
+        let machine = reader.read_u16::<LE>()?;
+        let number_of_sections = reader.read_u16::<LE>()?;
+        let time_date_stamp = reader.read_u32::<LE>()?;
+        // The COFF symbol table pointer and count are always zero in images.
+        reader.skip(8)?;
+        let size_of_optional_header = reader.read_u16::<LE>()?;
+        let characteristics = reader.read_u16::<LE>()?;
+        let file_header = FileHeader { machine, number_of_sections, time_date_stamp, size_of_optional_header, characteristics };
+        if file_header.number_of_sections > MAX_SECTIONS {
+            return Err(InterpreterError::Malformed { what: "section count", offset: nt_offset as u64 + 6 });
+        }
+
+        let optional = reader.stream_position()?;
+        let magic = reader.peek_u16::<LE>(optional)?;
+        let (image_base, directory_count_at) = match magic {
+            0x10B => (reader.peek_u32::<LE>(optional + 28)? as u64, optional + 92),
+            0x20B => (reader.peek_u64::<LE>(optional + 24)?, optional + 108),
+            _ => return Err(InterpreterError::Malformed { what: "optional header magic", offset: optional }),
+        };
+        let directory_count = reader.peek_u32::<LE>(directory_count_at)?;
+        let fits = (directory_count_at + 4 + directory_count as u64 * 8) <= optional + file_header.size_of_optional_header as u64;
+        if directory_count > MAX_DIRECTORIES || !fits {
+            return Err(InterpreterError::Malformed { what: "data directory count", offset: directory_count_at });
+        }
+        let data_directories = reader.peek_array::<[u32; 2], LE>(directory_count_at + 4, directory_count as usize)?
+            .into_iter()
+            .map(|[virtual_address, size]| DataDirectory { virtual_address, size })
+            .collect();
+        let optional_header = OptionalHeader {
+            magic,
+            address_of_entry_point: reader.peek_u32::<LE>(optional + 16)?,
+            image_base,
+            section_alignment: reader.peek_u32::<LE>(optional + 32)?,
+            file_alignment: reader.peek_u32::<LE>(optional + 36)?,
+            size_of_image: reader.peek_u32::<LE>(optional + 56)?,
+            size_of_headers: reader.peek_u32::<LE>(optional + 60)?,
+            subsystem: reader.peek_u16::<LE>(optional + 68)?,
+            dll_characteristics: reader.peek_u16::<LE>(optional + 70)?,
+            data_directories,
+        };
+
+        reader.seek(SeekFrom::Start(optional + file_header.size_of_optional_header as u64))?;
+        let mut sections = Vec::with_capacity(file_header.number_of_sections as usize);
+        for _ in 0..file_header.number_of_sections {
+            let name = reader.read_fixed_cstr_encoded(8, Encoding::Latin1)?;
+            let virtual_size = reader.read_u32::<LE>()?;
+            let virtual_address = reader.read_u32::<LE>()?;
+            let size_of_raw_data = reader.read_u32::<LE>()?;
+            let pointer_to_raw_data = reader.read_u32::<LE>()?;
+            reader.skip(12)?;
+            let characteristics = reader.read_u32::<LE>()?;
+            sections.push(Section { name, virtual_size, virtual_address, size_of_raw_data, pointer_to_raw_data, characteristics });
+        }
+        Ok(Pe { nt_offset, file_header, optional_header, sections })
+    }
+
+    pub fn is_pe32_plus(&self) -> bool {
+        self.optional_header.magic == 0x20B
+    }
+
+    pub fn pointer_width(&self) -> PointerWidth {
+        if self.is_pe32_plus() { PointerWidth::U64 } else { PointerWidth::U32 }
+    }
+
+    pub fn image_base(&self) -> u64 {
+        self.optional_header.image_base
+    }
+
+    pub fn directory(&self, index: usize) -> Option<DataDirectory> {
+        self.optional_header.data_directories.get(index)
+            .copied()
+            .filter(|d| d.virtual_address != 0)
+    }
+
+    pub fn section_containing(&self, rva: u32) -> Option<&Section> {
+        self.sections.iter().find(|s| (s.virtual_address..s.virtual_address.saturating_add(s.mapped_size())).contains(&rva))
+    }
+
+    /// The headers and every section, placed at `base` plus their RVA.
+    pub fn segments_at(&self, base: u64) -> Vec<Segment> {
+        let headers = self.optional_header.size_of_headers as u64;
+        let mut segments = vec![Segment::new(base, headers, 0, headers)];
+        segments.extend(self.sections.iter().map(|s| {
+            let size = s.mapped_size() as u64;
+            Segment::new(base + s.virtual_address as u64, size, s.pointer_to_raw_data as u64, (s.size_of_raw_data as u64).min(size))
+        }));
+        segments
+    }
+
+    /// The segments of the image loaded at its preferred base, for a `VaReader`.
+    pub fn segments(&self) -> Vec<Segment> {
+        self.segments_at(self.image_base())
+    }
+
+    /// Wraps the file in a reader addressed by virtual address, as the image would be loaded.
+    pub fn va_reader<R: Read + Seek>(&self, file: R) -> VaReader<R> {
+        VaReader::new(file, self.segments())
+    }
+
+    fn rva_reader<'a, R: Read + Seek + ?Sized>(&self, file: &'a mut R) -> VaReader<&'a mut R> {
+        VaReader::new(file, self.segments_at(0))
+    }
+
+    pub fn imports<R: Read + Seek + ?Sized>(&self, file: &mut R) -> Result<Vec<ImportedDll>, InterpreterError> {
+        let Some(directory) = self.directory(DIRECTORY_IMPORT) else { return Ok(Vec::new()) };
+        let mut image = self.rva_reader(file);
+        let width = self.pointer_width();
+        let ordinal_flag = 1u64 << (width.bytes() * 8 - 1);
+        let mut dlls = Vec::new();
+        for index in 0..MAX_TABLE_ENTRIES {
+            let descriptor = directory.virtual_address as u64 + index as u64 * 20;
+            let fields = image.peek_array::<u32, LE>(descriptor, 5)?;
+            let (lookup, name, iat) = (fields[0], fields[3], fields[4]);
+            if name == 0 && iat == 0 {
+                return Ok(dlls);
+            }
+            let name = image.peek_cstr_encoded(name as u64, Encoding::Latin1)?;
+            let thunks = if lookup != 0 { lookup } else { iat };
+            let mut functions = Vec::new();
+            for slot in 0..MAX_TABLE_ENTRIES {
+                let thunk = image.peek_pointer::<LE>(thunks as u64 + (slot * width.bytes()) as u64, width)?;
+                if thunk == 0 {
+                    break;
+                }
+                let iat_rva = iat + (slot * width.bytes()) as u32;
+                functions.push(if thunk & ordinal_flag != 0 {
+                    Import { name: None, hint: 0, ordinal: Some(thunk as u16), iat_rva }
+                } else {
+                    let by_name = thunk & 0x7FFF_FFFF;
+                    let hint = image.peek_u16::<LE>(by_name)?;
+                    let name = image.peek_cstr_encoded(by_name + 2, Encoding::Latin1)?;
+                    Import { name: Some(name), hint, ordinal: None, iat_rva }
+                });
+            }
+            dlls.push(ImportedDll { name, functions });
+        }
+        Err(InterpreterError::Malformed { what: "import directory", offset: directory.virtual_address as u64 })
+    }
+
+    pub fn exports<R: Read + Seek + ?Sized>(&self, file: &mut R) -> Result<Option<Exports>, InterpreterError> {
+        let Some(directory) = self.directory(DIRECTORY_EXPORT) else { return Ok(None) };
+        let mut image = self.rva_reader(file);
+        let [name, ordinal_base, function_count, name_count, functions_rva, names_rva, ordinals_rva] =
+            image.peek_array::<[u32; 7], LE>(directory.virtual_address as u64 + 12, 1)?[0];
+        if function_count as usize > MAX_TABLE_ENTRIES || name_count as usize > MAX_TABLE_ENTRIES {
+            return Err(InterpreterError::Malformed { what: "export directory", offset: directory.virtual_address as u64 });
+        }
+        let dll_name = image.peek_cstr_encoded(name as u64, Encoding::Latin1)?;
+        let addresses = image.peek_array::<u32, LE>(functions_rva as u64, function_count as usize)?;
+        let names = image.peek_array::<u32, LE>(names_rva as u64, name_count as usize)?;
+        let ordinals = image.peek_array::<u16, LE>(ordinals_rva as u64, name_count as usize)?;
+
+        let mut functions: Vec<Export> = addresses.iter()
+            .enumerate()
+            .map(|(i, &rva)| {
+                let ordinal = ordinal_base.checked_add(i as u32)
+                    .ok_or(InterpreterError::Malformed { what: "export ordinal base", offset: directory.virtual_address as u64 + 16 })?;
+                Ok(Export { ordinal, rva, name: None, forwarder: None })
+            })
+            .collect::<Result<_, InterpreterError>>()?;
+        for (&name, &index) in names.iter().zip(&ordinals) {
+            let export = functions.get_mut(index as usize)
+                .ok_or(InterpreterError::Malformed { what: "export ordinal", offset: ordinals_rva as u64 })?;
+            export.name = Some(image.peek_cstr_encoded(name as u64, Encoding::Latin1)?);
+        }
+        functions.retain(|f| f.rva != 0);
+        for export in functions.iter_mut().filter(|f| directory.contains(f.rva)) {
+            export.forwarder = Some(image.peek_cstr_encoded(export.rva as u64, Encoding::Latin1)?);
+        }
+        Ok(Some(Exports { dll_name, ordinal_base, functions }))
+    }
+
+    /// Base relocations, without the `ABSOLUTE` entries used as padding.
+    pub fn relocations<R: Read + Seek + ?Sized>(&self, file: &mut R) -> Result<Vec<Relocation>, InterpreterError> {
+        let Some(directory) = self.directory(DIRECTORY_BASERELOC) else { return Ok(Vec::new()) };
+        let mut image = self.rva_reader(file);
+        let mut relocations = Vec::new();
+        let mut block = directory.virtual_address as u64;
+        let end = block + directory.size as u64;
+        while block + 8 <= end {
+            let page = image.peek_u32::<LE>(block)?;
+            let size = image.peek_u32::<LE>(block + 4)? as u64;
+            if size < 8 || block + size > end {
+                return Err(InterpreterError::Malformed { what: "relocation block", offset: block });
+            }
+            let entries = image.peek_array::<u16, LE>(block + 8, (size as usize - 8) / 2)?;
+            for entry in entries.into_iter().filter(|entry| entry >> 12 != 0) {
+                let rva = page.checked_add((entry & 0xFFF) as u32)
+                    .ok_or(InterpreterError::Malformed { what: "relocation entry", offset: block })?;
+                relocations.push(Relocation { rva, kind: (entry >> 12) as u8 });
+            }
+            block += size;
+        }
+        Ok(relocations)
+    }
+
+    /// The x64 exception directory's function table.
+    pub fn exception_functions<R: Read + Seek + ?Sized>(&self, file: &mut R) -> Result<Vec<RuntimeFunction>, InterpreterError> {
+        let Some(directory) = self.directory(DIRECTORY_EXCEPTION) else { return Ok(Vec::new()) };
+        let mut image = self.rva_reader(file);
+        let count = directory.size as usize / 12;
+        Ok(image.peek_array::<[u32; 3], LE>(directory.virtual_address as u64, count)?
+            .into_iter()
+            .map(|[begin, end, unwind_info]| RuntimeFunction { begin, end, unwind_info })
+            .collect())
+    }
+}
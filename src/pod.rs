@@ -2,15 +2,17 @@
 
 use std::io::{Read, Write, Result as IoResult};
 use std::mem::{MaybeUninit, size_of};
+use byteorder::ByteOrder;
 
-/// #Safety
-/// Implementor must be a primitive type or plain-old-data struct
+/// # Safety
+/// Implementor must be a primitive type or plain-old-data struct: every bit pattern must be a valid value
+/// and the type must have no padding bytes, since values are viewed as raw bytes in both directions.
 pub unsafe trait Pod: Sized {
     fn read(reader: &mut impl Read) -> IoResult<Self> {
         let mut s: MaybeUninit<Self> = MaybeUninit::uninit();
 
         let as_slice = unsafe {
-            std::slice::from_raw_parts_mut((&mut s).as_ptr() as *mut u8, size_of::<Self>())
+            std::slice::from_raw_parts_mut(s.as_mut_ptr() as *mut u8, size_of::<Self>())
         };
         reader.read_exact(as_slice)?;
         Ok(unsafe { s.assume_init() })
@@ -25,8 +27,83 @@ pub unsafe trait Pod: Sized {
     }
 }
 
+/// Pod types that know how to reverse the byte order of their fields, so they can be read in bulk
+/// with `read_array`/`read_into`.
+pub trait EndianPod: Pod + Copy {
+    /// Reverses the byte order of every field in place.
+    fn swap_bytes(&mut self);
+}
+
+/// Returns true if values stored in `E` order must be byte swapped on this machine.
+pub(crate) fn needs_swap<E: ByteOrder>() -> bool {
+    E::read_u16(&1u16.to_ne_bytes()) != 1
+}
+
+/// Views a slice of Pod values as its raw bytes.
+pub(crate) fn as_bytes<T: Pod>(values: &[T]) -> &[u8] {
+    // Sound because `Pod` types have no padding, so every byte is initialized.
+    unsafe {
+        std::slice::from_raw_parts(values.as_ptr() as *const u8, std::mem::size_of_val(values))
+    }
+}
+
+/// Views a slice of Pod values as its raw bytes, for filling them in.
+pub(crate) fn as_bytes_mut<T: Pod>(values: &mut [T]) -> &mut [u8] {
+    unsafe {
+        std::slice::from_raw_parts_mut(values.as_mut_ptr() as *mut u8, std::mem::size_of_val(values))
+    }
+}
+
+/// Decodes a value stored in `E` order from the start of `bytes`.
+pub(crate) fn from_bytes<T: EndianPod, E: ByteOrder>(bytes: &[u8]) -> T {
+    // Pod types are valid for any bit pattern, including all zeroes.
+    let mut value: T = unsafe { std::mem::zeroed() };
+    as_bytes_mut(std::slice::from_mut(&mut value)).copy_from_slice(&bytes[..size_of::<T>()]);
+    if needs_swap::<E>() {
+        value.swap_bytes();
+    }
+    value
+}
+
 // Implement Pod for all constant sized slices of Pod
 unsafe impl<T: Pod, const SIZE: usize> Pod for [T; SIZE] {}
 
-// Implement Pod for u128 (may want to do this for all primitive types via a macro)
-unsafe impl Pod for u128 {}
\ No newline at end of file
+impl<T: EndianPod, const SIZE: usize> EndianPod for [T; SIZE] {
+    fn swap_bytes(&mut self) {
+        for value in self {
+            value.swap_bytes();
+        }
+    }
+}
+
+macro_rules! pod_int {
+    ($($ty:ty),*) => {
+        $(
+            unsafe impl Pod for $ty {}
+            impl EndianPod for $ty {
+                #[inline]
+                fn swap_bytes(&mut self) {
+                    *self = <$ty>::swap_bytes(*self);
+                }
+            }
+        )*
+    };
+}
+
+pod_int!(u8, i8, u16, i16, u32, i32, u64, i64, u128, i128);
+
+unsafe impl Pod for f32 {}
+impl EndianPod for f32 {
+    #[inline]
+    fn swap_bytes(&mut self) {
+        *self = f32::from_bits(self.to_bits().swap_bytes());
+    }
+}
+
+unsafe impl Pod for f64 {}
+impl EndianPod for f64 {
+    #[inline]
+    fn swap_bytes(&mut self) {
+        *self = f64::from_bits(self.to_bits().swap_bytes());
+    }
+}
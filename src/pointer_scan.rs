@@ -0,0 +1,212 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::ops::Range;
+use std::str::FromStr;
+use byteorder::ByteOrder;
+use crate::address::{Identity, Module, PointerExpr, PointerWidth};
+use crate::binary_reader::BinaryPeeker;
+use crate::error::InterpreterError;
+
+const CHUNK_SIZE: u64 = 1024 * 1024;
+
+/// A static pointer path: a module, an offset from its base to the first pointer, and the offsets
+/// added after each dereference. Written as `[["game.exe"+0x1F3A0]+0x18]+0x40`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PointerPath {
+    pub module: String,
+    /// `offsets[0]` is from the module base, the rest are applied after each dereference.
+    pub offsets: Vec<i64>,
+}
+
+impl PointerPath {
+    pub fn to_expr(&self) -> PointerExpr {
+        PointerExpr::chain(PointerExpr::Symbol(self.module.clone()), &self.offsets)
+    }
+
+    /// Reads a path back from an expression of the shape `to_expr` produces.
+    pub fn from_expr(expr: &PointerExpr) -> Option<PointerPath> {
+        let mut offsets = Vec::new();
+        let mut expr = expr;
+        loop {
+            let (inner, offset) = match expr {
+                PointerExpr::Add(a, b) => match **b {
+                    PointerExpr::Const(o) => (&**a, o),
+                    _ => return None,
+                },
+                PointerExpr::Sub(a, b) => match **b {
+                    PointerExpr::Const(o) => (&**a, o.wrapping_neg()),
+                    _ => return None,
+                },
+                other => (other, 0),
+            };
+            offsets.push(offset);
+            match inner {
+                PointerExpr::Deref(pointer) => expr = pointer,
+                PointerExpr::Symbol(module) => {
+                    offsets.reverse();
+                    return Some(PointerPath { module: module.clone(), offsets });
+                }
+                _ => return None,
+            }
+        }
+    }
+
+    /// Follows the path through `reader`, reading pointers as `width` bytes in `E` order.
+    pub fn resolve<R: BinaryPeeker + ?Sized, E: ByteOrder>(&self, reader: &mut R, width: PointerWidth, modules: &[Module]) -> Result<u64, InterpreterError> {
+        let base = |name: &str| Module::named(modules, name).map(|m| m.range.start);
+        Ok(reader.resolve_pointer_path::<E, _, _>(&self.to_expr(), width, &Identity, &base)?.va)
+    }
+
+    /// True if the path still leads to `target`, e.g. in a later snapshot or after the process restarted.
+    pub fn points_to<R: BinaryPeeker + ?Sized, E: ByteOrder>(&self, reader: &mut R, width: PointerWidth, modules: &[Module], target: u64) -> bool {
+        self.resolve::<R, E>(reader, width, modules).is_ok_and(|found| found == target)
+    }
+}
+
+impl fmt::Display for PointerPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_expr())
+    }
+}
+
+impl FromStr for PointerPath {
+    type Err = InterpreterError;
+
+    fn from_str(path: &str) -> Result<PointerPath, InterpreterError> {
+        PointerPath::from_expr(&path.parse()?)
+            .ok_or_else(|| InterpreterError::BadPointerExpr(format!("`{}` is not a module based pointer path", path)))
+    }
+}
+
+/// Finds pointer paths from a module to a target address, working backwards from the target through
+/// every pointer that lands at most `max_offset` bytes before it.
+#[derive(Clone, Debug)]
+pub struct PointerScanner {
+    width: PointerWidth,
+    max_depth: usize,
+    max_offset: u64,
+    max_results: usize,
+    align: u64,
+}
+
+impl PointerScanner {
+    /// Defaults to 4 levels, offsets up to 0x1000, 10000 results and pointers aligned to their width.
+    pub fn new(width: PointerWidth) -> Self {
+        PointerScanner { width, max_depth: 4, max_offset: 0x1000, max_results: 10_000, align: width.bytes() as u64 }
+    }
+
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    pub fn with_max_offset(mut self, max_offset: u64) -> Self {
+        self.max_offset = max_offset;
+        self
+    }
+
+    pub fn with_max_results(mut self, max_results: usize) -> Self {
+        self.max_results = max_results;
+        self
+    }
+
+    pub fn with_alignment(mut self, align: u64) -> Self {
+        self.align = align.max(1);
+        self
+    }
+
+    /// Searches `ranges` of `reader` for paths to `target` that start in one of `modules`. Only values that
+    /// point into `ranges` are treated as pointers. Pointers are `E` order.
+    pub fn scan<R: BinaryPeeker + ?Sized, E: ByteOrder>(&self, reader: &mut R, ranges: &[Range<u64>], modules: &[Module], target: u64) -> Result<Vec<PointerPath>, InterpreterError> {
+        let pointers = self.pointer_map::<R, E>(reader, ranges)?;
+        let mut search = Search::default();
+        self.search(&pointers, modules, target, &mut search);
+        Ok(search.results)
+    }
+
+    /// Every pointer-like value in `ranges`, as `(value, address)` sorted by value.
+    fn pointer_map<R: BinaryPeeker + ?Sized, E: ByteOrder>(&self, reader: &mut R, ranges: &[Range<u64>]) -> Result<Vec<(u64, u64)>, InterpreterError> {
+        let mut sorted = ranges.to_vec();
+        sorted.sort_by_key(|r| r.start);
+        let valid = |value: u64| {
+            let index = sorted.partition_point(|r| r.start <= value);
+            index > 0 && sorted[index - 1].contains(&value)
+        };
+        let size = self.width.bytes() as u64;
+        let step = CHUNK_SIZE.next_multiple_of(self.align);
+        let mut pointers = Vec::new();
+        for range in &sorted {
+            let mut base = range.start.next_multiple_of(self.align);
+            while base.saturating_add(size) <= range.end {
+                let len = (step + size - 1).min(range.end - base);
+                match reader.peek_bytes(base, len as usize) {
+                    Ok(chunk) => {
+                        for offset in (0..chunk.len() as u64 + 1 - size).step_by(self.align as usize) {
+                            let bytes = &chunk[offset as usize..(offset + size) as usize];
+                            let value = match self.width {
+                                PointerWidth::U32 => E::read_u32(bytes) as u64,
+                                PointerWidth::U64 => E::read_u64(bytes),
+                            };
+                            if value != 0 && valid(value) {
+                                pointers.push((value, base + offset));
+                            }
+                        }
+                    }
+                    Err(e) => log::debug!("skipping unreadable chunk at {:#x}: {}", base, e),
+                }
+                base += step;
+            }
+        }
+        pointers.sort_unstable();
+        Ok(pointers)
+    }
+
+    /// Extends `search.chain` backwards from `target`. Returns false if nothing was found below `target` and
+    /// nothing was skipped, so it can be remembered as a dead end.
+    fn search(&self, pointers: &[(u64, u64)], modules: &[Module], target: u64, search: &mut Search) -> bool {
+        let depth_left = self.max_depth.saturating_sub(search.chain.len());
+        if depth_left == 0 || search.dead_ends.get(&target).is_some_and(|&depth| depth >= depth_left) {
+            return false;
+        }
+        search.on_chain.insert(target);
+        let mut live = false;
+        let start = pointers.partition_point(|&(value, _)| value < target.saturating_sub(self.max_offset));
+        for &(value, address) in pointers[start..].iter().take_while(|&&(value, _)| value <= target) {
+            if search.results.len() >= self.max_results {
+                live = true;
+                break;
+            }
+            // Following a pointer back onto the chain would go round a cycle.
+            if search.on_chain.contains(&address) {
+                live = true;
+                continue;
+            }
+            search.chain.push((target - value) as i64);
+            if let Some(module) = Module::containing(modules, address) {
+                let mut offsets = vec![(address - module.range.start) as i64];
+                offsets.extend(search.chain.iter().rev());
+                search.results.push(PointerPath { module: module.name.clone(), offsets });
+                live = true;
+            }
+            live |= self.search(pointers, modules, address, search);
+            search.chain.pop();
+        }
+        search.on_chain.remove(&target);
+        if !live {
+            search.dead_ends.insert(target, depth_left);
+        }
+        live
+    }
+}
+
+/// State of one `PointerScanner::scan`.
+#[derive(Default)]
+struct Search {
+    /// Offsets found so far, innermost last.
+    chain: Vec<i64>,
+    /// Addresses the current chain passes through.
+    on_chain: HashSet<u64>,
+    /// Addresses known to lead to no module within the given number of levels.
+    dead_ends: HashMap<u64, usize>,
+    results: Vec<PointerPath>,
+}
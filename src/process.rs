@@ -0,0 +1,450 @@
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::marker::PhantomData;
+use std::ops::Range;
+use std::os::unix::fs::FileExt;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread::JoinHandle;
+use std::time::Duration;
+use byteorder::ByteOrder;
+use crate::address::Module;
+use crate::aob::{AobScanner, Pattern};
+use crate::error::InterpreterError;
+use crate::value_scan::{FirstScan, ScanValue, ValueScan};
+
+/// Access flags of a mapped region, from the `rwxp` column of `/proc/<pid>/maps`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Protection {
+    pub read: bool,
+    pub write: bool,
+    pub execute: bool,
+    /// Copy-on-write (`p`) rather than shared (`s`).
+    pub private: bool,
+}
+
+/// One line of `/proc/<pid>/maps`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MemoryRegion {
+    pub range: Range<u64>,
+    pub protection: Protection,
+    /// Offset into the backing file.
+    pub offset: u64,
+    /// Backing file, or a pseudo path such as `[heap]`. `None` for anonymous memory.
+    pub path: Option<String>,
+}
+
+impl MemoryRegion {
+    pub fn len(&self) -> u64 {
+        self.range.end - self.range.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.range.is_empty()
+    }
+
+    /// File name of the backing path, e.g. `game.exe` for a module mapped by Wine.
+    pub fn module_name(&self) -> Option<&str> {
+        let path = self.path.as_deref()?;
+        path.starts_with('/').then(|| path.rsplit('/').next().unwrap_or(path))
+    }
+}
+
+/// Selects regions of a process. Every criterion left unset matches any region.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RegionFilter {
+    module: Option<String>,
+    read: Option<bool>,
+    write: Option<bool>,
+    execute: Option<bool>,
+    private: Option<bool>,
+}
+
+impl RegionFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only regions backed by a file named `name`, compared case-insensitively as Windows does.
+    pub fn module(mut self, name: &str) -> Self {
+        self.module = Some(name.to_string());
+        self
+    }
+
+    pub fn readable(mut self, read: bool) -> Self {
+        self.read = Some(read);
+        self
+    }
+
+    pub fn writable(mut self, write: bool) -> Self {
+        self.write = Some(write);
+        self
+    }
+
+    pub fn executable(mut self, execute: bool) -> Self {
+        self.execute = Some(execute);
+        self
+    }
+
+    /// Only copy-on-write (`true`) or shared (`false`) regions.
+    pub fn private(mut self, private: bool) -> Self {
+        self.private = Some(private);
+        self
+    }
+
+    pub fn matches(&self, region: &MemoryRegion) -> bool {
+        let flag = |wanted: Option<bool>, actual: bool| wanted.is_none_or(|w| w == actual);
+        let protection = &region.protection;
+        flag(self.read, protection.read)
+            && flag(self.write, protection.write)
+            && flag(self.execute, protection.execute)
+            && flag(self.private, protection.private)
+            && self.module.as_deref().is_none_or(|m| region.module_name().is_some_and(|n| n.eq_ignore_ascii_case(m)))
+    }
+}
+
+fn parse_region(line: &str) -> Option<MemoryRegion> {
+    let mut fields = line.splitn(6, ' ');
+    let (start, end) = fields.next()?.split_once('-')?;
+    let range = u64::from_str_radix(start, 16).ok()?..u64::from_str_radix(end, 16).ok()?;
+    let flags = fields.next()?.as_bytes();
+    if flags.len() != 4 {
+        return None;
+    }
+    let protection = Protection {
+        read: flags[0] == b'r',
+        write: flags[1] == b'w',
+        execute: flags[2] == b'x',
+        private: flags[3] == b'p',
+    };
+    let offset = u64::from_str_radix(fields.next()?, 16).ok()?;
+    let _device = fields.next()?;
+    let _inode = fields.next()?;
+    let path = fields.next().map(str::trim_start).filter(|p| !p.is_empty()).map(str::to_string);
+    Some(MemoryRegion { range, protection, offset, path })
+}
+
+/// Parses the contents of a `/proc/<pid>/maps` file.
+pub fn parse_maps(maps: &str) -> Result<Vec<MemoryRegion>, InterpreterError> {
+    maps.lines()
+        .filter(|line| !line.is_empty())
+        .map(|line| parse_region(line).ok_or(InterpreterError::DecodeError("/proc/<pid>/maps")))
+        .collect()
+}
+
+/// errno values from `<asm-generic/errno-base.h>`, which every Linux architecture shares.
+const EIO: i32 = 5;
+const EFAULT: i32 = 14;
+
+/// The kernel reports pages it cannot access as EIO, and addresses outside any mapping as EFAULT.
+fn is_bad_page(e: &std::io::Error) -> bool {
+    matches!(e.raw_os_error(), Some(EIO) | Some(EFAULT))
+}
+
+fn unreadable(address: u64, e: std::io::Error) -> std::io::Error {
+    match is_bad_page(&e) {
+        true => std::io::Error::other(InterpreterError::UnreadableMemory(address)),
+        false => e,
+    }
+}
+
+fn unwritable(address: u64, e: std::io::Error) -> std::io::Error {
+    match is_bad_page(&e) {
+        true => std::io::Error::other(InterpreterError::UnwritableMemory(address)),
+        false => e,
+    }
+}
+
+/// Access marker of a `ProcessMemory` opened with `ProcessMemory::open`. It can only be read.
+#[derive(Clone, Copy, Debug)]
+pub struct ReadOnly;
+
+/// Access marker of a `ProcessMemory` opened with `ProcessMemory::open_writable`. It can be read and written.
+#[derive(Clone, Copy, Debug)]
+pub struct ReadWrite;
+
+/// The memory of a live Linux process, read through `/proc/<pid>/mem` and addressed by virtual address.
+///
+/// Implements `Read + Seek`, so every `BinaryReader`/`BinaryPeeker` method works on it. Reading a page the
+/// process has not mapped, or cannot be read, fails with an `io::Error` wrapping
+/// `InterpreterError::UnreadableMemory`. `SeekFrom::End` is relative to the end of the highest region.
+///
+/// `open_writable` returns a `ProcessMemory<ReadWrite>`, which also implements `Write`, so the
+/// `BinaryWriter`/`BinaryPoker` methods work too, and can apply patches and freeze values. Writes go
+/// through the kernel and ignore page protection, so code can be patched in place.
+pub struct ProcessMemory<A = ReadOnly> {
+    pid: u32,
+    mem: File,
+    position: u64,
+    access: PhantomData<A>,
+}
+
+impl ProcessMemory {
+    /// Opens the memory of `pid`. This needs ptrace access to the process, e.g. being its parent or root.
+    pub fn open(pid: u32) -> Result<Self, InterpreterError> {
+        let mem = File::open(format!("/proc/{}/mem", pid))?;
+        Ok(ProcessMemory { pid, mem, position: 0, access: PhantomData })
+    }
+}
+
+impl<A> ProcessMemory<A> {
+    pub fn pid(&self) -> u32 {
+        self.pid
+    }
+
+    /// Reads the current memory map of the process.
+    pub fn regions(&self) -> Result<Vec<MemoryRegion>, InterpreterError> {
+        parse_maps(&std::fs::read_to_string(format!("/proc/{}/maps", self.pid))?)
+    }
+
+    /// Reads the memory map and keeps the regions `filter` selects.
+    pub fn regions_matching(&self, filter: &RegionFilter) -> Result<Vec<MemoryRegion>, InterpreterError> {
+        Ok(self.regions()?.into_iter().filter(|r| filter.matches(r)).collect())
+    }
+
+    /// Groups the file-backed regions into modules, spanning from their lowest to their highest address.
+    pub fn modules(&self) -> Result<Vec<Module>, InterpreterError> {
+        let regions = self.regions()?;
+        Ok(Module::group(regions.iter()
+            .filter(|r| r.module_name().is_some())
+            .filter_map(|r| Some((r.path.as_deref()?, r.range.clone())))))
+    }
+
+    /// Scans the readable regions `filter` selects for `pattern`, a chunk at a time. Regions that become
+    /// unreadable while scanning yield an error and are skipped.
+    pub fn scan<'a>(&'a mut self, pattern: &'a Pattern, filter: &RegionFilter) -> Result<AobScanner<'a, Self>, InterpreterError> {
+        let filter = filter.clone().readable(true);
+        let ranges: Vec<Range<u64>> = self.regions_matching(&filter)?.into_iter().map(|r| r.range).collect();
+        Ok(pattern.scan_ranges(self, ranges))
+    }
+
+    /// Returns the addresses of every naturally aligned `value` stored in `E` order in the regions `filter`
+    /// selects. Chunks that cannot be read are skipped. Use `ValueScan` directly to narrow the results down.
+    pub fn scan_value<T: ScanValue, E: ByteOrder>(&mut self, value: T, filter: &RegionFilter) -> Result<Vec<u64>, InterpreterError> {
+        let filter = filter.clone().readable(true);
+        let ranges: Vec<Range<u64>> = self.regions_matching(&filter)?.into_iter().map(|r| r.range).collect();
+        let align = std::mem::align_of::<T>() as u64;
+        let scan = ValueScan::<T, E>::first_scan(self, ranges, align, FirstScan::Exact(value))?;
+        Ok(scan.iter().map(|(address, _)| address).collect())
+    }
+
+    /// Reads at `address` without moving the cursor. May read fewer bytes than requested at the end of a region.
+    pub fn read_at(&self, address: u64, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.mem.read_at(buf, address).map_err(|e| unreadable(address, e))
+    }
+
+    pub fn read_exact_at(&self, address: u64, buf: &mut [u8]) -> std::io::Result<()> {
+        let mut done = 0;
+        while done < buf.len() {
+            match self.read_at(address + done as u64, &mut buf[done..])? {
+                0 => return Err(std::io::Error::other(InterpreterError::UnreadableMemory(address + done as u64))),
+                read => done += read,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ProcessMemory<ReadWrite> {
+    /// Opens the memory of `pid` for reading and writing.
+    pub fn open_writable(pid: u32) -> Result<Self, InterpreterError> {
+        let mem = OpenOptions::new().read(true).write(true).open(format!("/proc/{}/mem", pid))?;
+        Ok(ProcessMemory { pid, mem, position: 0, access: PhantomData })
+    }
+
+    /// Writes at `address` without moving the cursor.
+    pub fn write_at(&self, address: u64, buf: &[u8]) -> std::io::Result<usize> {
+        self.mem.write_at(buf, address).map_err(|e| unwritable(address, e))
+    }
+
+    pub fn write_all_at(&self, address: u64, buf: &[u8]) -> std::io::Result<()> {
+        let mut done = 0;
+        while done < buf.len() {
+            match self.write_at(address + done as u64, &buf[done..])? {
+                0 => return Err(std::io::Error::other(InterpreterError::UnwritableMemory(address + done as u64))),
+                written => done += written,
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes `bytes` at `address` and returns a `Patch` that puts the original bytes back when dropped.
+    /// The patch holds its own handle to the process, so the memory can still be used while it is applied.
+    pub fn patch(&self, address: u64, bytes: &[u8]) -> Result<Patch, InterpreterError> {
+        let mut original = vec![0; bytes.len()];
+        self.read_exact_at(address, &mut original)?;
+        let memory = ProcessMemory { pid: self.pid, mem: self.mem.try_clone()?, position: 0, access: PhantomData };
+        memory.write_all_at(address, bytes)?;
+        Ok(Patch { memory, address, original: Some(original) })
+    }
+
+    /// Applies several patches together. If one fails, the ones already applied are restored.
+    pub fn patch_all(&self, patches: &[(u64, &[u8])]) -> Result<PatchSet, InterpreterError> {
+        let mut set = PatchSet { patches: Vec::with_capacity(patches.len()) };
+        for &(address, bytes) in patches {
+            set.patches.push(self.patch(address, bytes)?);
+        }
+        Ok(set)
+    }
+
+    /// Writes `bytes` at `address` now and again every `interval` from a background thread, so the process
+    /// cannot change the value, until the returned `Freeze` is dropped. The last written bytes stay in place.
+    pub fn freeze(&self, address: u64, bytes: &[u8], interval: Duration) -> Result<Freeze, InterpreterError> {
+        self.write_all_at(address, bytes)?;
+        let memory = ProcessMemory { pid: self.pid, mem: self.mem.try_clone()?, position: 0, access: PhantomData };
+        let bytes = bytes.to_vec();
+        let (stop, stopped) = mpsc::channel::<()>();
+        let thread = std::thread::spawn(move || {
+            while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                if let Err(e) = memory.write_all_at(address, &bytes) {
+                    log::warn!("could not refresh frozen value at {:#x} in process {}: {}", address, memory.pid, e);
+                    break;
+                }
+            }
+        });
+        Ok(Freeze { address, stop: Some(stop), thread: Some(thread) })
+    }
+}
+
+/// A value kept in place by `ProcessMemory::freeze`. Rewriting stops on drop.
+pub struct Freeze {
+    address: u64,
+    stop: Option<Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Freeze {
+    pub fn address(&self) -> u64 {
+        self.address
+    }
+}
+
+impl Drop for Freeze {
+    fn drop(&mut self) {
+        // Dropping the sender wakes the thread up with a disconnect.
+        self.stop.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Bytes written over process memory by `ProcessMemory::patch`. The original bytes are restored by
+/// `restore`, or on drop, unless `keep` is called.
+pub struct Patch {
+    memory: ProcessMemory<ReadWrite>,
+    address: u64,
+    original: Option<Vec<u8>>,
+}
+
+impl Patch {
+    pub fn address(&self) -> u64 {
+        self.address
+    }
+
+    pub fn original(&self) -> &[u8] {
+        self.original.as_deref().unwrap_or_default()
+    }
+
+    pub fn restore(mut self) -> Result<(), InterpreterError> {
+        self.restore_original()
+    }
+
+    /// Leaves the patched bytes in place.
+    pub fn keep(mut self) {
+        self.original = None;
+    }
+
+    fn restore_original(&mut self) -> Result<(), InterpreterError> {
+        match self.original.take() {
+            Some(original) => Ok(self.memory.write_all_at(self.address, &original)?),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for Patch {
+    fn drop(&mut self) {
+        if let Err(e) = self.restore_original() {
+            log::warn!("could not restore patch at {:#x} in process {}: {}", self.address, self.memory.pid, e);
+        }
+    }
+}
+
+/// Patches applied together by `ProcessMemory::patch_all`. They are restored in reverse order, so
+/// overlapping patches unwind correctly.
+pub struct PatchSet {
+    patches: Vec<Patch>,
+}
+
+impl PatchSet {
+    pub fn patches(&self) -> &[Patch] {
+        &self.patches
+    }
+
+    pub fn restore(mut self) -> Result<(), InterpreterError> {
+        self.restore_all()
+    }
+
+    pub fn keep(mut self) {
+        for patch in self.patches.drain(..) {
+            patch.keep();
+        }
+    }
+
+    fn restore_all(&mut self) -> Result<(), InterpreterError> {
+        let mut result = Ok(());
+        while let Some(mut patch) = self.patches.pop() {
+            if let Err(e) = patch.restore_original() {
+                result = result.and(Err(e));
+            }
+        }
+        result
+    }
+}
+
+impl Drop for PatchSet {
+    fn drop(&mut self) {
+        if let Err(e) = self.restore_all() {
+            log::warn!("could not restore patches: {}", e);
+        }
+    }
+}
+
+impl<A> Read for ProcessMemory<A> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.read_at(self.position, buf)?;
+        self.position += read as u64;
+        Ok(read)
+    }
+}
+
+impl Write for ProcessMemory<ReadWrite> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = self.write_at(self.position, buf)?;
+        self.position += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<A> Seek for ProcessMemory<A> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let (base, delta) = match pos {
+            SeekFrom::Start(position) => {
+                self.position = position;
+                return Ok(position);
+            }
+            SeekFrom::Current(delta) => (self.position, delta),
+            SeekFrom::End(delta) => {
+                let regions = self.regions().map_err(std::io::Error::other)?;
+                (regions.iter().map(|r| r.range.end).max().unwrap_or(0), delta)
+            }
+        };
+        self.position = base.checked_add_signed(delta)
+            .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "invalid seek to a negative or overflowing position"))?;
+        Ok(self.position)
+    }
+}
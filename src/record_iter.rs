@@ -0,0 +1,141 @@
+use std::io::{Read, Seek};
+use std::marker::PhantomData;
+use byteorder::ByteOrder;
+use crate::binary_reader::{BinaryPeeker, BinaryReader};
+use crate::error::InterpreterError;
+use crate::pod::EndianPod;
+
+fn read_record<T: EndianPod, E: ByteOrder, R: Read + ?Sized>(reader: &mut R) -> std::io::Result<T> {
+    // Pod types are valid for any bit pattern, including all zeroes.
+    let mut record: T = unsafe { std::mem::zeroed() };
+    reader.read_into::<T, E>(std::slice::from_mut(&mut record))?;
+    Ok(record)
+}
+
+fn element_error(index: usize, source: impl Into<InterpreterError>) -> InterpreterError {
+    InterpreterError::Element { index, source: Box::new(source.into()) }
+}
+
+/// Reads `count` consecutive records. Returned by `BinaryReader::read_iter`.
+pub struct RecordIter<'a, R: ?Sized, T, E> {
+    reader: &'a mut R,
+    index: usize,
+    count: usize,
+    _marker: PhantomData<(T, E)>,
+}
+
+impl<'a, R: ?Sized, T, E> RecordIter<'a, R, T, E> {
+    pub(crate) fn new(reader: &'a mut R, count: usize) -> Self {
+        RecordIter { reader, index: 0, count, _marker: PhantomData }
+    }
+}
+
+impl<R: Read + ?Sized, T: EndianPod, E: ByteOrder> Iterator for RecordIter<'_, R, T, E> {
+    type Item = Result<T, InterpreterError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index >= self.count {
+            return None;
+        }
+        let index = self.index;
+        self.index += 1;
+        match read_record::<T, E, R>(self.reader) {
+            Ok(record) => Some(Ok(record)),
+            Err(e) => {
+                self.index = self.count;
+                Some(Err(element_error(index, e)))
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.count - self.index;
+        (remaining, Some(remaining))
+    }
+}
+
+/// Peeks `count` records placed `stride` bytes apart without moving the cursor.
+/// Returned by `BinaryPeeker::strided_iter`.
+pub struct StridedIter<'a, R: ?Sized, T, E> {
+    reader: &'a mut R,
+    offset: u64,
+    stride: u64,
+    index: usize,
+    count: usize,
+    _marker: PhantomData<(T, E)>,
+}
+
+impl<'a, R: ?Sized, T, E> StridedIter<'a, R, T, E> {
+    pub(crate) fn new(reader: &'a mut R, offset: u64, stride: u64, count: usize) -> Self {
+        StridedIter { reader, offset, stride, index: 0, count, _marker: PhantomData }
+    }
+}
+
+impl<R: Read + Seek + ?Sized, T: EndianPod, E: ByteOrder> Iterator for StridedIter<'_, R, T, E> {
+    type Item = Result<T, InterpreterError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index >= self.count {
+            return None;
+        }
+        let index = self.index;
+        self.index += 1;
+        let position = (index as u64).checked_mul(self.stride).and_then(|o| o.checked_add(self.offset));
+        let Some(position) = position else {
+            self.index = self.count;
+            return Some(Err(element_error(index, InterpreterError::Malformed { what: "record offset", offset: self.offset })));
+        };
+        let mut record: T = unsafe { std::mem::zeroed() };
+        match self.reader.peek_into::<T, E>(position, std::slice::from_mut(&mut record)) {
+            Ok(()) => Some(Ok(record)),
+            Err(e) => {
+                self.index = self.count;
+                Some(Err(element_error(index, e)))
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.count - self.index;
+        (remaining, Some(remaining))
+    }
+}
+
+/// Reads records until one matches the sentinel predicate. The sentinel is consumed but not yielded.
+/// Returned by `BinaryReader::read_until_sentinel`.
+pub struct SentinelIter<'a, R: ?Sized, T, E, F> {
+    reader: &'a mut R,
+    is_sentinel: F,
+    index: usize,
+    done: bool,
+    _marker: PhantomData<(T, E)>,
+}
+
+impl<'a, R: ?Sized, T, E, F> SentinelIter<'a, R, T, E, F> {
+    pub(crate) fn new(reader: &'a mut R, is_sentinel: F) -> Self {
+        SentinelIter { reader, is_sentinel, index: 0, done: false, _marker: PhantomData }
+    }
+}
+
+impl<R: Read + ?Sized, T: EndianPod, E: ByteOrder, F: FnMut(&T) -> bool> Iterator for SentinelIter<'_, R, T, E, F> {
+    type Item = Result<T, InterpreterError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let index = self.index;
+        self.index += 1;
+        match read_record::<T, E, R>(self.reader) {
+            Ok(record) if (self.is_sentinel)(&record) => {
+                self.done = true;
+                None
+            }
+            Ok(record) => Some(Ok(record)),
+            Err(e) => {
+                self.done = true;
+                Some(Err(element_error(index, e)))
+            }
+        }
+    }
+}
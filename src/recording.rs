@@ -0,0 +1,253 @@
+use std::fmt::Debug;
+use std::io::{Read, Seek, SeekFrom};
+use byteorder::{ByteOrder, ReadBytesExt};
+use crate::binary_reader::{BinaryPeeker, BinaryReader, LengthPrefix};
+use crate::coverage::Coverage;
+use crate::encoding::Encoding;
+use crate::endian::Endian;
+use crate::error::InterpreterError;
+use crate::hex_dump;
+use crate::pod::EndianPod;
+
+/// Generates inherent methods that shadow the reader traits' methods, so reads made directly on a
+/// `RecordingReader` are recorded with their type and value.
+macro_rules! typed_reads {
+    ($($tr:ident { $(fn $name:ident $(<$($g:ident: $bound:ident),*>)? ($($arg:ident: $ty:ty),*) -> $ret:ty;)* })*) => {
+        $($(
+            #[doc = concat!("Calls `", stringify!($tr), "::", stringify!($name), "` and records the span it read with the type and value it returned.")]
+            pub fn $name $(<$($g: $bound),*>)? (&mut self $(, $arg: $ty)*) -> $ret {
+                self.typed(|r| $tr::$name $(::<$($g),*>)? (r $(, $arg)*))
+            }
+        )*)*
+    };
+}
+
+/// One span of bytes consumed through a `RecordingReader`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReadRecord {
+    pub offset: u64,
+    pub len: u64,
+    /// Type the bytes were decoded as. `None` for reads that reached the wrapper through generic `Read` code.
+    pub type_name: Option<&'static str>,
+    /// `Debug` rendering of the decoded value.
+    pub value: Option<String>,
+    pub label: Option<String>,
+}
+
+impl ReadRecord {
+    pub fn end(&self) -> u64 {
+        self.offset + self.len
+    }
+}
+
+/// Wraps a `Read + Seek` source and records every byte range read through it, so the reads a parser made
+/// can be rendered as an annotated hex dump.
+///
+/// The `read_*`/`peek_*` methods called on the wrapper itself record the type and value they decoded. Reads
+/// made through a generic `R: Read` bound are logged as one untyped span per `read` call. Wrap a read in
+/// `record`, or use `read_as`/`peek_as`, to also attach a label.
+pub struct RecordingReader<R> {
+    inner: R,
+    position: u64,
+    records: Vec<ReadRecord>,
+    pending: Vec<ReadRecord>,
+    depth: usize,
+}
+
+impl<R: Read + Seek> RecordingReader<R> {
+    pub fn new(mut inner: R) -> std::io::Result<Self> {
+        let position = inner.stream_position()?;
+        Ok(RecordingReader { inner, position, records: Vec::new(), pending: Vec::new(), depth: 0 })
+    }
+
+    pub fn records(&self) -> &[ReadRecord] {
+        &self.records
+    }
+
+    pub fn clear(&mut self) {
+        self.records.clear();
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Runs `read` and records every span it consumed under `label`, with the type and value it returned.
+    /// Spans already claimed by a nested `record` keep their own annotation.
+    pub fn record<T: Debug, E, F>(&mut self, label: &str, read: F) -> Result<T, E>
+    where
+        F: FnOnce(&mut Self) -> Result<T, E>,
+    {
+        self.capture(Some(label), read)
+    }
+
+    /// Reads a `T` stored in `E` order and records it under `label`.
+    pub fn read_as<T: EndianPod + Debug, E: ByteOrder>(&mut self, label: &str) -> std::io::Result<T> {
+        self.record(label, |r| {
+            // Pod types are valid for any bit pattern, including all zeroes.
+            let mut value: T = unsafe { std::mem::zeroed() };
+            r.read_into::<T, E>(std::slice::from_mut(&mut value))?;
+            Ok(value)
+        })
+    }
+
+    /// Peeks a `T` stored in `E` order at `position` and records it under `label`.
+    pub fn peek_as<T: EndianPod + Debug, E: ByteOrder>(&mut self, position: u64, label: &str) -> std::io::Result<T> {
+        self.record(label, |r| {
+            let mut value: T = unsafe { std::mem::zeroed() };
+            r.peek_into::<T, E>(position, std::slice::from_mut(&mut value))?;
+            Ok(value)
+        })
+    }
+
+    /// Reports the byte ranges no recorded read touched, the ranges read as conflicting types and the ranges
+    /// more than one read overlapped.
+    pub fn coverage(&mut self) -> std::io::Result<Coverage> {
+        let len = self.inner.total_len()?;
+        Ok(Coverage::new(&self.records, len))
+    }
+
+    /// Renders the whole source as a hex dump with the recorded reads listed in the margin.
+    pub fn render_text(&mut self) -> Result<String, InterpreterError> {
+        let data = self.inner.peek_rest(0)?;
+        Ok(hex_dump::render_text(&data, &self.records))
+    }
+
+    /// Renders the whole source as a self-contained HTML page with colour-coded reads.
+    pub fn render_html(&mut self) -> Result<String, InterpreterError> {
+        let data = self.inner.peek_rest(0)?;
+        Ok(hex_dump::render_html(&data, &self.records))
+    }
+
+    typed_reads! {
+        ReadBytesExt {
+            fn read_u8() -> std::io::Result<u8>;
+            fn read_i8() -> std::io::Result<i8>;
+            fn read_u16<E: ByteOrder>() -> std::io::Result<u16>;
+            fn read_i16<E: ByteOrder>() -> std::io::Result<i16>;
+            fn read_u32<E: ByteOrder>() -> std::io::Result<u32>;
+            fn read_i32<E: ByteOrder>() -> std::io::Result<i32>;
+            fn read_u64<E: ByteOrder>() -> std::io::Result<u64>;
+            fn read_i64<E: ByteOrder>() -> std::io::Result<i64>;
+            fn read_u128<E: ByteOrder>() -> std::io::Result<u128>;
+            fn read_i128<E: ByteOrder>() -> std::io::Result<i128>;
+            fn read_f32<E: ByteOrder>() -> std::io::Result<f32>;
+            fn read_f64<E: ByteOrder>() -> std::io::Result<f64>;
+        }
+        BinaryReader {
+            fn read_bytes(size: usize) -> std::io::Result<Vec<u8>>;
+            fn read_cstr() -> std::io::Result<String>;
+            fn read_fixed_cstr(size: usize) -> std::io::Result<String>;
+            fn read_cstr_encoded(encoding: Encoding) -> Result<String, InterpreterError>;
+            fn read_fixed_cstr_encoded(size: usize, encoding: Encoding) -> Result<String, InterpreterError>;
+            fn read_pstr<P: LengthPrefix, E: ByteOrder>(encoding: Encoding) -> Result<String, InterpreterError>;
+            fn read_dotnet_string() -> Result<String, InterpreterError>;
+            fn read_7bit_encoded_i32() -> Result<i32, InterpreterError>;
+            fn read_uleb128() -> Result<u64, InterpreterError>;
+            fn read_sleb128() -> Result<i64, InterpreterError>;
+            fn read_varint_zigzag() -> Result<i64, InterpreterError>;
+            fn read_prefix_varint() -> Result<u64, InterpreterError>;
+            fn read_u16_endian(endian: Endian) -> std::io::Result<u16>;
+            fn read_i16_endian(endian: Endian) -> std::io::Result<i16>;
+            fn read_u32_endian(endian: Endian) -> std::io::Result<u32>;
+            fn read_i32_endian(endian: Endian) -> std::io::Result<i32>;
+            fn read_u64_endian(endian: Endian) -> std::io::Result<u64>;
+            fn read_i64_endian(endian: Endian) -> std::io::Result<i64>;
+        }
+        BinaryPeeker {
+            fn peek_u8(position: u64) -> std::io::Result<u8>;
+            fn peek_i8(position: u64) -> std::io::Result<i8>;
+            fn peek_u16<E: ByteOrder>(position: u64) -> std::io::Result<u16>;
+            fn peek_i16<E: ByteOrder>(position: u64) -> std::io::Result<i16>;
+            fn peek_u32<E: ByteOrder>(position: u64) -> std::io::Result<u32>;
+            fn peek_i32<E: ByteOrder>(position: u64) -> std::io::Result<i32>;
+            fn peek_u64<E: ByteOrder>(position: u64) -> std::io::Result<u64>;
+            fn peek_i64<E: ByteOrder>(position: u64) -> std::io::Result<i64>;
+            fn peek_u128<E: ByteOrder>(position: u64) -> std::io::Result<u128>;
+            fn peek_i128<E: ByteOrder>(position: u64) -> std::io::Result<i128>;
+            fn peek_bytes(position: u64, size: usize) -> std::io::Result<Vec<u8>>;
+            fn peek_cstr(position: u64) -> std::io::Result<String>;
+            fn peek_fixed_cstr(position: u64, size: usize) -> std::io::Result<String>;
+            fn peek_cstr_encoded(position: u64, encoding: Encoding) -> Result<String, InterpreterError>;
+            fn peek_fixed_cstr_encoded(position: u64, size: usize, encoding: Encoding) -> Result<String, InterpreterError>;
+            fn peek_pstr<P: LengthPrefix, E: ByteOrder>(position: u64, encoding: Encoding) -> Result<String, InterpreterError>;
+            fn peek_dotnet_string(position: u64) -> Result<String, InterpreterError>;
+            fn peek_uleb128(position: u64) -> Result<u64, InterpreterError>;
+            fn peek_sleb128(position: u64) -> Result<i64, InterpreterError>;
+            fn peek_varint_zigzag(position: u64) -> Result<i64, InterpreterError>;
+            fn peek_prefix_varint(position: u64) -> Result<u64, InterpreterError>;
+            fn peek_u16_endian(position: u64, endian: Endian) -> std::io::Result<u16>;
+            fn peek_i16_endian(position: u64, endian: Endian) -> std::io::Result<i16>;
+            fn peek_u32_endian(position: u64, endian: Endian) -> std::io::Result<u32>;
+            fn peek_i32_endian(position: u64, endian: Endian) -> std::io::Result<i32>;
+            fn peek_u64_endian(position: u64, endian: Endian) -> std::io::Result<u64>;
+            fn peek_i64_endian(position: u64, endian: Endian) -> std::io::Result<i64>;
+        }
+    }
+
+    /// Runs a typed read. Inside `record` the enclosing record claims its spans instead.
+    fn typed<T: Debug, E, F>(&mut self, read: F) -> Result<T, E>
+    where
+        F: FnOnce(&mut Self) -> Result<T, E>,
+    {
+        if self.depth > 0 {
+            return read(self);
+        }
+        self.capture(None, read)
+    }
+
+    /// Runs `read` and records the spans it consumed as one record per contiguous run, with the type and
+    /// value it returned.
+    fn capture<T: Debug, E, F>(&mut self, label: Option<&str>, read: F) -> Result<T, E>
+    where
+        F: FnOnce(&mut Self) -> Result<T, E>,
+    {
+        let mark = self.pending.len();
+        self.depth += 1;
+        let result = read(self);
+        self.depth -= 1;
+        let spans: Vec<ReadRecord> = self.pending.drain(mark..).collect();
+        let value = result.as_ref().ok().map(|v| format!("{:?}", v));
+        let mut claimed: Vec<ReadRecord> = Vec::new();
+        for span in spans {
+            match claimed.last_mut() {
+                Some(last) if last.end() == span.offset => last.len += span.len,
+                _ => claimed.push(ReadRecord {
+                    type_name: Some(std::any::type_name::<T>()),
+                    value: value.clone(),
+                    label: label.map(str::to_string),
+                    ..span
+                }),
+            }
+        }
+        self.records.extend(claimed);
+        result
+    }
+
+    fn log_read(&mut self, offset: u64, len: u64) {
+        let log = if self.depth == 0 { &mut self.records } else { &mut self.pending };
+        log.push(ReadRecord { offset, len, type_name: None, value: None, label: None });
+    }
+}
+
+impl<R: Read + Seek> Read for RecordingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        if read > 0 {
+            self.log_read(self.position, read as u64);
+            self.position += read as u64;
+        }
+        Ok(read)
+    }
+}
+
+impl<R: Read + Seek> Seek for RecordingReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.position = self.inner.seek(pos)?;
+        Ok(self.position)
+    }
+}
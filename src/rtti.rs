@@ -0,0 +1,425 @@
+use std::collections::HashMap;
+use std::ops::Range;
+use byteorder::LittleEndian as LE;
+use crate::address::PointerWidth;
+use crate::binary_reader::BinaryPeeker;
+use crate::encoding::Encoding;
+use crate::error::InterpreterError;
+use crate::pe::Pe;
+
+const CHUNK_SIZE: u64 = 1024 * 1024;
+/// Upper bound on a class hierarchy, so a false positive cannot make the walk read a huge array.
+const MAX_BASES: u32 = 4096;
+const IMAGE_SCN_MEM_EXECUTE: u32 = 0x2000_0000;
+
+/// `RTTICompleteObjectLocator`, found in the slot just before a vtable.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CompleteObjectLocator {
+    pub address: u64,
+    /// 0 in x86 images, whose RTTI holds addresses, and 1 in x64 images, whose RTTI holds RVAs.
+    pub signature: u32,
+    /// Offset of the vtable's subobject within the complete object.
+    pub offset: u32,
+    pub cd_offset: u32,
+    pub type_descriptor: u64,
+    pub class_hierarchy: u64,
+}
+
+/// An entry of a class's base class array, read from its `RTTIBaseClassDescriptor`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BaseClass {
+    /// Demangled name, or the decorated one if it could not be demangled.
+    pub name: String,
+    pub decorated: String,
+    pub type_descriptor: u64,
+    /// How many of the following array entries are this class's own bases.
+    pub contained_bases: u32,
+    pub mdisp: i32,
+    pub pdisp: i32,
+    pub vdisp: i32,
+    pub attributes: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Vtable {
+    pub address: u64,
+    pub locator: u64,
+    /// Offset of the subobject using this vtable, 0 for the primary one.
+    pub offset: u32,
+}
+
+/// A polymorphic class with its vtables and the bases listed in its class hierarchy descriptor.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Class {
+    /// Demangled name, or the decorated one if it could not be demangled.
+    pub name: String,
+    pub decorated: String,
+    pub type_descriptor: u64,
+    /// `RTTIClassHierarchyDescriptor` attributes: bit 0 for multiple and bit 1 for virtual inheritance.
+    pub hierarchy_attributes: u32,
+    /// Every direct and indirect base, depth first, without the class itself.
+    pub bases: Vec<BaseClass>,
+    pub vtables: Vec<Vtable>,
+}
+
+impl Class {
+    /// The bases the class derives from directly.
+    pub fn direct_bases(&self) -> Vec<&BaseClass> {
+        let mut direct = Vec::new();
+        let mut index = 0;
+        while let Some(base) = self.bases.get(index) {
+            direct.push(base);
+            index += 1 + base.contained_bases as usize;
+        }
+        direct
+    }
+
+    /// Names from the class down through each first base, e.g. `["Player", "Actor", "Object"]`.
+    pub fn inheritance_chain(&self) -> Vec<&str> {
+        let mut chain = vec![self.name.as_str()];
+        for base in &self.bases {
+            chain.push(&base.name);
+            if base.contained_bases == 0 {
+                break;
+            }
+        }
+        chain
+    }
+}
+
+/// Reads MSVC RTTI through a reader addressed by virtual address, such as a `VaReader` over a PE file,
+/// a `Snapshot` or a `ProcessMemory`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rtti {
+    image_base: u64,
+    width: PointerWidth,
+}
+
+impl Rtti {
+    /// `image_base` is where the module is loaded, which x64 RTTI offsets are relative to.
+    pub fn new(image_base: u64, width: PointerWidth) -> Self {
+        Rtti { image_base, width }
+    }
+
+    /// For the image loaded at its preferred base.
+    pub fn for_pe(pe: &Pe) -> Self {
+        Rtti::new(pe.image_base(), pe.pointer_width())
+    }
+
+    /// The non executable sections of `pe` at its preferred base, where vtables and RTTI live.
+    pub fn pe_data_ranges(pe: &Pe) -> Vec<Range<u64>> {
+        pe.sections.iter()
+            .filter(|s| s.characteristics & IMAGE_SCN_MEM_EXECUTE == 0)
+            .map(|s| {
+                let start = pe.image_base() + s.virtual_address as u64;
+                start..start + s.mapped_size() as u64
+            })
+            .collect()
+    }
+
+    fn resolve(&self, value: u32) -> u64 {
+        match self.width {
+            PointerWidth::U32 => value as u64,
+            PointerWidth::U64 => self.image_base + value as u64,
+        }
+    }
+
+    /// Reads and checks the locator at `address`: its signature, its self reference on x64, and that its
+    /// type descriptor holds a decorated class name.
+    pub fn locator<R: BinaryPeeker + ?Sized>(&self, reader: &mut R, address: u64) -> Result<CompleteObjectLocator, InterpreterError> {
+        let malformed = InterpreterError::Malformed { what: "complete object locator", offset: address };
+        let [signature, offset, cd_offset, type_descriptor, class_hierarchy] = reader.peek_array::<[u32; 5], LE>(address, 1)?[0];
+        match self.width {
+            PointerWidth::U32 if signature != 0 => return Err(malformed),
+            PointerWidth::U64 if signature != 1 || self.resolve(reader.peek_u32::<LE>(address + 20)?) != address => return Err(malformed),
+            _ => {}
+        }
+        let locator = CompleteObjectLocator {
+            address,
+            signature,
+            offset,
+            cd_offset,
+            type_descriptor: self.resolve(type_descriptor),
+            class_hierarchy: self.resolve(class_hierarchy),
+        };
+        if !reader.has_magic(self.type_name_address(locator.type_descriptor), b".?A")? {
+            return Err(malformed);
+        }
+        Ok(locator)
+    }
+
+    /// Reads the locator in the slot before `vtable`.
+    pub fn locator_for_vtable<R: BinaryPeeker + ?Sized>(&self, reader: &mut R, vtable: u64) -> Result<CompleteObjectLocator, InterpreterError> {
+        let slot = vtable.checked_sub(self.width.bytes() as u64)
+            .ok_or(InterpreterError::Malformed { what: "vtable", offset: vtable })?;
+        let address = reader.peek_pointer::<LE>(slot, self.width)?;
+        self.locator(reader, address)
+    }
+
+    fn type_name_address(&self, type_descriptor: u64) -> u64 {
+        // The name follows the type_info vtable pointer and a spare pointer.
+        type_descriptor + 2 * self.width.bytes() as u64
+    }
+
+    /// The decorated name in a `TypeDescriptor`, e.g. `.?AVPlayer@game@@`.
+    pub fn type_name<R: BinaryPeeker + ?Sized>(&self, reader: &mut R, type_descriptor: u64) -> Result<String, InterpreterError> {
+        reader.peek_cstr_encoded(self.type_name_address(type_descriptor), Encoding::Latin1)
+    }
+
+    /// Reads a class hierarchy descriptor, returning its attributes and its base class array. The first
+    /// entry of the array is the class itself.
+    pub fn base_classes<R: BinaryPeeker + ?Sized>(&self, reader: &mut R, class_hierarchy: u64) -> Result<(u32, Vec<BaseClass>), InterpreterError> {
+        let [signature, attributes, count, array] = reader.peek_array::<[u32; 4], LE>(class_hierarchy, 1)?[0];
+        if signature != 0 || count == 0 || count > MAX_BASES {
+            return Err(InterpreterError::Malformed { what: "class hierarchy descriptor", offset: class_hierarchy });
+        }
+        let mut bases = Vec::with_capacity(count as usize);
+        for descriptor in reader.peek_array::<u32, LE>(self.resolve(array), count as usize)? {
+            let descriptor = self.resolve(descriptor);
+            let [type_descriptor, contained_bases, mdisp, pdisp, vdisp, attributes] = reader.peek_array::<[u32; 6], LE>(descriptor, 1)?[0];
+            let type_descriptor = self.resolve(type_descriptor);
+            let decorated = self.type_name(reader, type_descriptor)?;
+            bases.push(BaseClass {
+                name: demangle(&decorated).unwrap_or_else(|| decorated.clone()),
+                decorated,
+                type_descriptor,
+                contained_bases,
+                mdisp: mdisp as i32,
+                pdisp: pdisp as i32,
+                vdisp: vdisp as i32,
+                attributes,
+            });
+        }
+        Ok((attributes, bases))
+    }
+
+    /// The class a locator describes, with only the vtable `vtable` listed.
+    pub fn class<R: BinaryPeeker + ?Sized>(&self, reader: &mut R, locator: &CompleteObjectLocator, vtable: u64) -> Result<Class, InterpreterError> {
+        let decorated = self.type_name(reader, locator.type_descriptor)?;
+        let (hierarchy_attributes, mut bases) = self.base_classes(reader, locator.class_hierarchy)?;
+        bases.remove(0);
+        Ok(Class {
+            name: demangle(&decorated).unwrap_or_else(|| decorated.clone()),
+            decorated,
+            type_descriptor: locator.type_descriptor,
+            hierarchy_attributes,
+            bases,
+            vtables: vec![Vtable { address: vtable, locator: locator.address, offset: locator.offset }],
+        })
+    }
+
+    /// Finds every vtable in `ranges` by looking for aligned pointers to valid locators, and groups them by
+    /// class. Classes are ordered by their first vtable; each class's vtables by subobject offset.
+    pub fn find_classes<R: BinaryPeeker + ?Sized>(&self, reader: &mut R, ranges: &[Range<u64>]) -> Result<Vec<Class>, InterpreterError> {
+        let mut sorted = ranges.to_vec();
+        sorted.sort_by_key(|r| r.start);
+        let in_ranges = |value: u64| {
+            let index = sorted.partition_point(|r| r.start <= value);
+            index > 0 && sorted[index - 1].contains(&value)
+        };
+
+        let size = self.width.bytes() as u64;
+        let mut candidates = Vec::new();
+        for range in &sorted {
+            let mut base = range.start.next_multiple_of(size);
+            while base.saturating_add(size) <= range.end {
+                let len = (CHUNK_SIZE + size - 1).min(range.end - base);
+                match reader.peek_bytes(base, len as usize) {
+                    Ok(chunk) => {
+                        for offset in (0..chunk.len() as u64 + 1 - size).step_by(size as usize) {
+                            let bytes = &chunk[offset as usize..(offset + size) as usize];
+                            let value = match self.width {
+                                PointerWidth::U32 => u32::from_le_bytes(bytes.try_into().unwrap()) as u64,
+                                PointerWidth::U64 => u64::from_le_bytes(bytes.try_into().unwrap()),
+                            };
+                            if in_ranges(value) {
+                                candidates.push((base + offset + size, value));
+                            }
+                        }
+                    }
+                    Err(e) => log::debug!("skipping unreadable chunk at {:#x}: {}", base, e),
+                }
+                base += CHUNK_SIZE;
+            }
+        }
+
+        let mut locators: HashMap<u64, Option<CompleteObjectLocator>> = HashMap::new();
+        let mut classes: Vec<Class> = Vec::new();
+        let mut by_type: HashMap<u64, usize> = HashMap::new();
+        for (vtable, address) in candidates {
+            let locator = *locators.entry(address).or_insert_with(|| self.locator(reader, address).ok());
+            let Some(locator) = locator else { continue };
+            match by_type.get(&locator.type_descriptor) {
+                Some(&index) => classes[index].vtables.push(Vtable { address: vtable, locator: address, offset: locator.offset }),
+                None => match self.class(reader, &locator, vtable) {
+                    Ok(class) => {
+                        by_type.insert(locator.type_descriptor, classes.len());
+                        classes.push(class);
+                    }
+                    Err(e) => log::debug!("skipping vtable at {:#x}: {}", vtable, e),
+                },
+            }
+        }
+        for class in &mut classes {
+            class.vtables.sort_by_key(|v| (v.offset, v.address));
+        }
+        Ok(classes)
+    }
+}
+
+/// Demangles an MSVC type descriptor name such as `.?AVPlayer@game@@` into `game::Player`.
+///
+/// Handles namespaces, nested classes, name back-references and templates over classes, enums, pointers,
+/// integers and the builtin types. Returns `None` for anything else.
+pub fn demangle(decorated: &str) -> Option<String> {
+    let rest = decorated.strip_prefix(".?A")?;
+    if !matches!(rest.as_bytes().first()?, b'V' | b'U' | b'T') {
+        return None;
+    }
+    let mut demangler = Demangler { input: rest.as_bytes(), position: 1, names: Vec::new() };
+    let name = demangler.qualified_name()?;
+    (demangler.position == demangler.input.len()).then_some(name)
+}
+
+struct Demangler<'a> {
+    input: &'a [u8],
+    position: usize,
+    /// Names a single digit can refer back to.
+    names: Vec<String>,
+}
+
+impl Demangler<'_> {
+    fn next(&mut self) -> Option<u8> {
+        let byte = *self.input.get(self.position)?;
+        self.position += 1;
+        Some(byte)
+    }
+
+    fn eat(&mut self, prefix: &[u8]) -> bool {
+        let found = self.input[self.position..].starts_with(prefix);
+        if found {
+            self.position += prefix.len();
+        }
+        found
+    }
+
+    fn memorize(&mut self, name: &str) {
+        if self.names.len() < 10 && !self.names.iter().any(|n| n == name) {
+            self.names.push(name.to_string());
+        }
+    }
+
+    /// Name fragments, innermost first, up to a closing `@`.
+    fn qualified_name(&mut self) -> Option<String> {
+        let mut parts = Vec::new();
+        while !self.eat(b"@") {
+            parts.push(self.fragment()?);
+        }
+        if parts.is_empty() {
+            return None;
+        }
+        parts.reverse();
+        Some(parts.join("::"))
+    }
+
+    fn fragment(&mut self) -> Option<String> {
+        if let Some(&digit @ b'0'..=b'9') = self.input.get(self.position) {
+            self.position += 1;
+            return self.names.get((digit - b'0') as usize).cloned();
+        }
+        if self.eat(b"?$") {
+            // A template instance's arguments have their own back-references.
+            let outer = std::mem::take(&mut self.names);
+            let name = self.identifier()?;
+            let mut arguments = Vec::new();
+            while !self.eat(b"@") {
+                arguments.push(self.argument()?);
+            }
+            self.names = outer;
+            let instance = format!("{}<{}>", name, arguments.join(","));
+            self.memorize(&instance);
+            return Some(instance);
+        }
+        self.identifier()
+    }
+
+    fn identifier(&mut self) -> Option<String> {
+        let length = self.input[self.position..].iter().position(|&b| b == b'@')?;
+        let name = std::str::from_utf8(&self.input[self.position..self.position + length]).ok()?.to_string();
+        if name.is_empty() || name.starts_with('?') {
+            return None;
+        }
+        self.position += length + 1;
+        self.memorize(&name);
+        Some(name)
+    }
+
+    fn argument(&mut self) -> Option<String> {
+        if self.eat(b"$0") {
+            return self.number().map(|n| n.to_string());
+        }
+        self.data_type()
+    }
+
+    /// An encoded integer: `0`-`9` for 1 to 10, otherwise hex digits written `A`-`P` ended by `@`.
+    fn number(&mut self) -> Option<i64> {
+        let negative = self.eat(b"?");
+        let value = match self.next()? {
+            digit @ b'0'..=b'9' => (digit - b'0') as i64 + 1,
+            b'@' => 0,
+            mut letter => {
+                let mut value = 0i64;
+                while letter != b'@' {
+                    if !(b'A'..=b'P').contains(&letter) {
+                        return None;
+                    }
+                    value = value.checked_mul(16)? + (letter - b'A') as i64;
+                    letter = self.next()?;
+                }
+                value
+            }
+        };
+        Some(if negative { -value } else { value })
+    }
+
+    fn data_type(&mut self) -> Option<String> {
+        let builtin = match self.next()? {
+            b'C' => "signed char",
+            b'D' => "char",
+            b'E' => "unsigned char",
+            b'F' => "short",
+            b'G' => "unsigned short",
+            b'H' => "int",
+            b'I' => "unsigned int",
+            b'J' => "long",
+            b'K' => "unsigned long",
+            b'M' => "float",
+            b'N' => "double",
+            b'O' => "long double",
+            b'X' => "void",
+            b'V' | b'U' | b'T' => return self.qualified_name(),
+            b'W' => {
+                self.next()?;
+                return self.qualified_name();
+            }
+            b'P' => {
+                self.eat(b"E");
+                let constness = match self.next()? {
+                    b'A' => "",
+                    b'B' => "const ",
+                    _ => return None,
+                };
+                return Some(format!("{}{}*", constness, self.data_type()?));
+            }
+            b'_' => match self.next()? {
+                b'N' => "bool",
+                b'J' => "__int64",
+                b'K' => "unsigned __int64",
+                b'W' => "wchar_t",
+                _ => return None,
+            },
+            _ => return None,
+        };
+        Some(builtin.to_string())
+    }
+}
@@ -0,0 +1,221 @@
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+use byteorder::{ByteOrder, LittleEndian, ReadBytesExt, WriteBytesExt};
+use crate::address::Module;
+use crate::binary_reader::{BinaryPeeker, BinaryReader};
+use crate::binary_writer::{BinaryPoker, BinaryWriter};
+use crate::encoding::Encoding;
+use crate::error::InterpreterError;
+use crate::pod::{EndianPod, from_bytes};
+use crate::va_reader::{Segment, VaReader};
+
+pub const SNAPSHOT_MAGIC: &[u8; 8] = b"BISNAP\r\n";
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+const CHUNK_SIZE: u64 = 1024 * 1024;
+
+/// One captured region, as listed in a snapshot's region table.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SnapshotRegion {
+    pub range: Range<u64>,
+    /// Where the region's bytes start, relative to the start of the snapshot.
+    pub data_offset: u64,
+    /// Backing path of the region when it was captured, e.g. a module path or `[heap]`.
+    pub path: Option<String>,
+}
+
+/// Copies `regions` of `reader` into `out` as a snapshot file and returns the region table written.
+///
+/// The file is little endian: the magic, a `u32` version, a `u32` region count and a `u64` offset to the
+/// region table, then the region data, then the table. Each table entry is the region's start address,
+/// size and data offset as `u64`s followed by its path as a `u16` length prefixed UTF-8 string.
+///
+/// A region that stops being readable part way through is cut short there; unreadable regions are left out.
+/// Offsets are relative to where the snapshot starts in `out`, so it can be embedded in a larger file and
+/// opened with `Snapshot::open` from the same position.
+pub fn capture<R, W, I>(reader: &mut R, regions: I, out: &mut W) -> Result<Vec<SnapshotRegion>, InterpreterError>
+where
+    R: BinaryPeeker + ?Sized,
+    W: Write + Seek,
+    I: IntoIterator<Item = (Range<u64>, Option<String>)>,
+{
+    let start = out.stream_position()?;
+    out.write_bytes(SNAPSHOT_MAGIC)?;
+    out.write_u32::<LittleEndian>(SNAPSHOT_VERSION)?;
+    let count_at = out.stream_position()?;
+    out.write_u32::<LittleEndian>(0)?;
+    out.write_u64::<LittleEndian>(0)?;
+
+    let mut table = Vec::new();
+    for (range, path) in regions {
+        let data_offset = out.stream_position()? - start;
+        let mut end = range.start;
+        while end < range.end {
+            let size = CHUNK_SIZE.min(range.end - end);
+            match reader.peek_bytes(end, size as usize) {
+                Ok(chunk) => out.write_bytes(&chunk)?,
+                Err(e) => {
+                    log::debug!("stopping capture of {:#x}..{:#x} at {:#x}: {}", range.start, range.end, end, e);
+                    break;
+                }
+            }
+            end += size;
+        }
+        if end > range.start {
+            table.push(SnapshotRegion { range: range.start..end, data_offset, path });
+        }
+    }
+
+    let table_offset = out.stream_position()? - start;
+    for region in &table {
+        out.write_u64::<LittleEndian>(region.range.start)?;
+        out.write_u64::<LittleEndian>(region.range.end - region.range.start)?;
+        out.write_u64::<LittleEndian>(region.data_offset)?;
+        out.write_pstr::<u16, LittleEndian>(region.path.as_deref().unwrap_or_default(), Encoding::Utf8)?;
+    }
+    out.poke_u32::<LittleEndian>(count_at, table.len() as u32)?;
+    out.poke_u64::<LittleEndian>(count_at + 4, table_offset)?;
+    Ok(table)
+}
+
+/// A snapshot file opened for reading. `reader` addresses it by virtual address, so the captured memory
+/// can be read with every `BinaryReader`/`BinaryPeeker` method, scanned, or diffed against another snapshot.
+pub struct Snapshot<R> {
+    regions: Vec<SnapshotRegion>,
+    reader: VaReader<R>,
+}
+
+impl<R: Read + Seek> Snapshot<R> {
+    /// Reads a snapshot starting at the current position of `inner`, where `capture` started writing it.
+    pub fn open(mut inner: R) -> Result<Self, InterpreterError> {
+        let base = inner.stream_position()?;
+        inner.expect_magic(SNAPSHOT_MAGIC)?;
+        let version = inner.read_u32::<LittleEndian>()?;
+        if version != SNAPSHOT_VERSION {
+            return Err(InterpreterError::UnsupportedVersion { what: "snapshot", found: version, expected: SNAPSHOT_VERSION });
+        }
+        let count = inner.read_u32::<LittleEndian>()?;
+        let table_offset = inner.read_u64::<LittleEndian>()?;
+        inner.seek(SeekFrom::Start(base + table_offset))?;
+        let mut regions = Vec::new();
+        for _ in 0..count {
+            let start = inner.read_u64::<LittleEndian>()?;
+            let size = inner.read_u64::<LittleEndian>()?;
+            let data_offset = inner.read_u64::<LittleEndian>()?;
+            let path = inner.read_pstr::<u16, LittleEndian>(Encoding::Utf8)?;
+            let end = start.checked_add(size).ok_or(InterpreterError::DecodeError("snapshot region table"))?;
+            regions.push(SnapshotRegion { range: start..end, data_offset, path: (!path.is_empty()).then_some(path) });
+        }
+        let segments = regions.iter()
+            .map(|r| Segment::new(r.range.start, r.range.end - r.range.start, base + r.data_offset, r.range.end - r.range.start))
+            .collect();
+        Ok(Snapshot { regions, reader: VaReader::new(inner, segments) })
+    }
+
+    pub fn regions(&self) -> &[SnapshotRegion] {
+        &self.regions
+    }
+
+    pub fn ranges(&self) -> Vec<Range<u64>> {
+        self.regions.iter().map(|r| r.range.clone()).collect()
+    }
+
+    /// Modules of the captured process, from the regions backed by files.
+    pub fn modules(&self) -> Vec<Module> {
+        Module::group(self.regions.iter()
+            .filter_map(|r| Some((r.path.as_deref()?, r.range.clone())))
+            .filter(|(path, _)| !path.starts_with('[')))
+    }
+
+    pub fn reader(&mut self) -> &mut VaReader<R> {
+        &mut self.reader
+    }
+
+    pub fn into_reader(self) -> VaReader<R> {
+        self.reader
+    }
+
+    /// Compares the addresses captured in both snapshots. See `diff`.
+    pub fn diff<S: Read + Seek>(&mut self, after: &mut Snapshot<S>, align: u64) -> Result<Vec<Change>, InterpreterError> {
+        let mut common = Vec::new();
+        for a in &self.regions {
+            for b in &after.regions {
+                let range = a.range.start.max(b.range.start)..a.range.end.min(b.range.end);
+                if !range.is_empty() {
+                    common.push(range);
+                }
+            }
+        }
+        common.sort_by_key(|r| r.start);
+        diff(&mut self.reader, &mut after.reader, common, align)
+    }
+}
+
+/// A run of bytes that differs between two captures, widened to whole `align` sized slots.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Change {
+    pub range: Range<u64>,
+    pub before: Vec<u8>,
+    pub after: Vec<u8>,
+}
+
+impl Change {
+    /// Reads the change as consecutive `T` values stored in `E` order, as `(address, before, after)`,
+    /// leaving out values that did not change.
+    pub fn values<T: EndianPod + PartialEq, E: ByteOrder>(&self) -> Vec<(u64, T, T)> {
+        let size = size_of::<T>();
+        self.before.chunks_exact(size)
+            .zip(self.after.chunks_exact(size))
+            .enumerate()
+            .filter(|(_, (before, after))| before != after)
+            .map(|(i, (before, after))| (self.range.start + (i * size) as u64, from_bytes::<T, E>(before), from_bytes::<T, E>(after)))
+            .collect()
+    }
+}
+
+/// Compares `ranges` of two sources and returns the bytes that differ. Each difference is widened to
+/// `align` boundaries and touching differences are merged, so the changes can be read as typed values
+/// with `Change::values`.
+pub fn diff<A, B, I>(before: &mut A, after: &mut B, ranges: I, align: u64) -> Result<Vec<Change>, InterpreterError>
+where
+    A: BinaryPeeker + ?Sized,
+    B: BinaryPeeker + ?Sized,
+    I: IntoIterator<Item = Range<u64>>,
+{
+    let align = align.max(1);
+    let mut changes: Vec<Change> = Vec::new();
+    for range in ranges {
+        let mut position = range.start;
+        while position < range.end {
+            let size = CHUNK_SIZE.min(range.end - position);
+            let old = before.peek_bytes(position, size as usize)?;
+            let new = after.peek_bytes(position, size as usize)?;
+            let mut i = 0;
+            while i < old.len() {
+                if old[i] == new[i] {
+                    i += 1;
+                    continue;
+                }
+                let mut end = i + 1;
+                while end < old.len() && old[end] != new[end] {
+                    end += 1;
+                }
+                let start = (position + i as u64) / align * align;
+                let end_address = (position + end as u64).next_multiple_of(align);
+                let slot = start.max(range.start)..end_address.min(range.end);
+                match changes.last_mut() {
+                    Some(last) if last.range.end >= slot.start => last.range.end = last.range.end.max(slot.end),
+                    _ => changes.push(Change { range: slot, before: Vec::new(), after: Vec::new() }),
+                }
+                i = end;
+            }
+            position += size;
+        }
+    }
+    for change in &mut changes {
+        let len = (change.range.end - change.range.start) as usize;
+        change.before = before.peek_bytes(change.range.start, len)?;
+        change.after = after.peek_bytes(change.range.start, len)?;
+    }
+    Ok(changes)
+}
@@ -0,0 +1,250 @@
+use std::collections::{HashMap, VecDeque};
+use byteorder::ByteOrder;
+use crate::binary_reader::BinaryPeeker;
+use crate::encoding::Encoding;
+use crate::error::InterpreterError;
+use crate::guess::SlotWidth;
+
+const CHUNK_SIZE: u64 = 64 * 1024;
+const MAX_RUN_BYTES: usize = 4096;
+
+/// A run of printable text found by `StringScanner`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FoundString {
+    pub offset: u64,
+    pub encoding: Encoding,
+    pub text: String,
+}
+
+/// A found string together with the offsets of the values that point at it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StringXref {
+    /// Index into the slice passed to `find_string_refs`.
+    pub index: usize,
+    pub referenced_from: Vec<u64>,
+}
+
+fn is_narrow_text(byte: u8) -> bool {
+    is_ascii_text(byte) || byte == b'\t' || byte >= 0x80
+}
+
+fn is_wide_text(unit: u16) -> bool {
+    let [high, low] = unit.to_be_bytes();
+    match unit {
+        0x09 | 0x20..=0x7E | 0xA0..=0xFF => true,
+        // Kana and CJK ideographs. Units made of two ASCII bytes, or with a zero low byte, are far more
+        // likely to be narrow text or misaligned wide text than real ideographs.
+        0x3000..=0x30FF | 0x4E00..=0x9FFF => low != 0 && !(is_ascii_text(high) && is_ascii_text(low)),
+        _ => false,
+    }
+}
+
+fn is_ascii_text(byte: u8) -> bool {
+    (0x20..0x7F).contains(&byte)
+}
+
+/// Text printable enough to report, once decoded.
+fn is_printable(text: &str) -> bool {
+    text.chars().all(|c| !c.is_control() || c == '\t')
+}
+
+/// Collects one candidate run for one encoding.
+struct Run {
+    encoding: Encoding,
+    start: u64,
+    bytes: Vec<u8>,
+    first: Option<u8>,
+}
+
+impl Run {
+    fn new(encoding: Encoding) -> Run {
+        Run { encoding, start: 0, bytes: Vec::new(), first: None }
+    }
+
+    fn push(&mut self, offset: u64, byte: u8, min_len: usize, found: &mut Vec<FoundString>) {
+        match self.encoding.unit_size() {
+            1 => {
+                if is_narrow_text(byte) {
+                    self.extend(offset, &[byte], min_len, found);
+                } else {
+                    self.flush(min_len, found);
+                }
+            }
+            _ => {
+                if offset.is_multiple_of(2) {
+                    self.first = Some(byte);
+                    return;
+                }
+                let Some(first) = self.first.take() else { return };
+                let pair = [first, byte];
+                let unit = match self.encoding {
+                    Encoding::Utf16BE => u16::from_be_bytes(pair),
+                    _ => u16::from_le_bytes(pair),
+                };
+                if is_wide_text(unit) {
+                    self.extend(offset - 1, &pair, min_len, found);
+                } else {
+                    self.flush(min_len, found);
+                }
+            }
+        }
+    }
+
+    fn extend(&mut self, offset: u64, bytes: &[u8], min_len: usize, found: &mut Vec<FoundString>) {
+        if self.bytes.is_empty() {
+            self.start = offset;
+        }
+        self.bytes.extend_from_slice(bytes);
+        if self.bytes.len() >= MAX_RUN_BYTES {
+            self.flush(min_len, found);
+        }
+    }
+
+    fn flush(&mut self, min_len: usize, found: &mut Vec<FoundString>) {
+        if self.bytes.len() >= min_len * self.encoding.unit_size() {
+            match self.encoding.decode(&self.bytes) {
+                Ok(text) => self.emit(self.start, text, min_len, found),
+                // Not valid UTF-8 as a whole, so fall back to the plain ASCII parts of the run.
+                Err(_) => {
+                    let mut start = 0;
+                    for part in self.bytes.split(|b| !b.is_ascii()) {
+                        let text = String::from_utf8_lossy(part).into_owned();
+                        self.emit(self.start + start as u64, text, min_len, found);
+                        start += part.len() + 1;
+                    }
+                }
+            }
+        }
+        self.bytes.clear();
+    }
+
+    fn emit(&self, offset: u64, text: String, min_len: usize, found: &mut Vec<FoundString>) {
+        if text.chars().count() >= min_len && is_printable(&text) {
+            found.push(FoundString { offset, encoding: self.encoding, text });
+        }
+    }
+
+    /// Lowest offset a string this run has yet to emit can start at, given the scan has reached `position`.
+    fn earliest(&self, position: u64) -> u64 {
+        if !self.bytes.is_empty() {
+            self.start
+        } else if self.first.is_some() {
+            position - 1
+        } else {
+            position
+        }
+    }
+}
+
+/// Walks a `Read + Seek` source in chunks, like the `strings` tool, yielding every run of printable
+/// text of at least `min_len` characters in each of the requested encodings. Strings are yielded in
+/// offset order. The cursor is not moved.
+pub struct StringScanner<'a, R: ?Sized> {
+    reader: &'a mut R,
+    min_len: usize,
+    runs: Vec<Run>,
+    position: u64,
+    len: Option<u64>,
+    found: VecDeque<FoundString>,
+    /// Strings found ahead of a run still open from an earlier offset.
+    pending: Vec<FoundString>,
+}
+
+impl<'a, R: BinaryPeeker + ?Sized> StringScanner<'a, R> {
+    /// Scans for UTF-8 (which covers ASCII), UTF-16LE and UTF-16BE text.
+    pub fn new(reader: &'a mut R, min_len: usize) -> Self {
+        Self::with_encodings(reader, min_len, &[Encoding::Utf8, Encoding::Utf16LE, Encoding::Utf16BE])
+    }
+
+    /// Scans for text in `encodings`. Wide encodings are scanned at even offsets.
+    pub fn with_encodings(reader: &'a mut R, min_len: usize, encodings: &[Encoding]) -> Self {
+        let runs = encodings.iter().map(|&encoding| Run::new(encoding)).collect();
+        StringScanner { reader, min_len: min_len.max(1), runs, position: 0, len: None, found: VecDeque::new(), pending: Vec::new() }
+    }
+
+    fn scan_chunk(&mut self) -> Result<bool, InterpreterError> {
+        let len = match self.len {
+            Some(len) => len,
+            None => *self.len.insert(self.reader.total_len()?),
+        };
+        if self.position >= len {
+            return Ok(false);
+        }
+        let size = CHUNK_SIZE.min(len - self.position);
+        let chunk = self.reader.peek_bytes(self.position, size as usize)?;
+        let mut found = std::mem::take(&mut self.pending);
+        for (i, &byte) in chunk.iter().enumerate() {
+            for run in self.runs.iter_mut() {
+                run.push(self.position + i as u64, byte, self.min_len, &mut found);
+            }
+        }
+        self.position += size;
+        let ready = if self.position >= len {
+            for run in self.runs.iter_mut() {
+                run.flush(self.min_len, &mut found);
+            }
+            u64::MAX
+        } else {
+            // A run that carries into the next chunk can still emit a string starting before the ones found here.
+            self.runs.iter().map(|run| run.earliest(self.position)).min().unwrap_or(self.position)
+        };
+        found.sort_by_key(|s| s.offset);
+        let split = found.partition_point(|s| s.offset < ready);
+        self.pending = found.split_off(split);
+        self.found.extend(found);
+        Ok(true)
+    }
+}
+
+impl<R: BinaryPeeker + ?Sized> Iterator for StringScanner<'_, R> {
+    type Item = Result<FoundString, InterpreterError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.found.is_empty() {
+            match self.scan_chunk() {
+                Ok(true) => {}
+                Ok(false) => return None,
+                Err(e) => {
+                    self.len = Some(0);
+                    return Some(Err(e));
+                }
+            }
+        }
+        self.found.pop_front().map(Ok)
+    }
+}
+
+/// Looks for `width` sized values stored in `E` order, aligned to their size, that equal
+/// `base + string.offset` for one of `strings`. Pass the image base as `base` when the file holds
+/// addresses rather than file offsets. Only strings with at least one reference are returned.
+pub fn find_string_refs<R: BinaryPeeker + ?Sized, E: ByteOrder>(reader: &mut R, strings: &[FoundString], width: SlotWidth, base: u64) -> Result<Vec<StringXref>, InterpreterError> {
+    let targets: HashMap<u64, usize> = strings.iter()
+        .enumerate()
+        .map(|(i, s)| (base.wrapping_add(s.offset), i))
+        .collect();
+    let mut refs: HashMap<usize, Vec<u64>> = HashMap::new();
+
+    let len = reader.total_len()?;
+    let step = width.bytes() as u64;
+    let mut position = 0;
+    while position + step <= len {
+        let size = CHUNK_SIZE.min(len - position) / step * step;
+        let chunk = reader.peek_bytes(position, size as usize)?;
+        for (i, value) in chunk.chunks_exact(step as usize).enumerate() {
+            let value = match width {
+                SlotWidth::Four => E::read_u32(value) as u64,
+                SlotWidth::Eight => E::read_u64(value),
+            };
+            if let Some(&index) = targets.get(&value) {
+                refs.entry(index).or_default().push(position + i as u64 * step);
+            }
+        }
+        position += size;
+    }
+
+    let mut xrefs: Vec<StringXref> = refs.into_iter()
+        .map(|(index, referenced_from)| StringXref { index, referenced_from })
+        .collect();
+    xrefs.sort_by_key(|x| x.index);
+    Ok(xrefs)
+}
@@ -30,7 +30,7 @@ macro_rules! peek_type {
                 self.seek(SeekFrom::Start(position))?;
                 let byte = self.[<read_ $ty>]::<T>();
                 self.seek(SeekFrom::Start(start))?;
-                return byte;
+                byte
             }
 
             #[doc = "Seeks to position from current position of the stream and reads a `" $ty "` type then returns to original position" ]
@@ -39,10 +39,59 @@ macro_rules! peek_type {
                 self.seek(SeekFrom::Current(position as i64))?;
                 let byte = self.[<read_ $ty>]::<T>();
                 self.seek(SeekFrom::Start(start))?;
-                return byte;
+                byte
             }
         }
     };
 }
 
 
+
+#[macro_export]
+/// generates poke_type functions.
+macro_rules! poke_type {
+    ($ty:ty) => {
+        paste! {
+            #[doc = "Seeks to position from start of the stream and writes a `" $ty "` type then returns to original position" ]
+            fn [<poke_ $ty>]<T: ByteOrder>(&mut self, position: u64, value: $ty) -> std::io::Result<()> {
+                let start = self.stream_position()?;
+                self.seek(SeekFrom::Start(position))?;
+                let result = self.[<write_ $ty>]::<T>(value);
+                self.seek(SeekFrom::Start(start))?;
+                result
+            }
+        }
+    };
+}
+
+#[macro_export]
+/// generates read_type_endian functions.
+macro_rules! read_type_endian {
+    ($ty:ty) => {
+        paste! {
+            #[doc = "Reads a `" $ty "` type in a byte order chosen at runtime and advances the cursor by the size of the type" ]
+            fn [<read_ $ty _endian>](&mut self, endian: Endian) -> std::io::Result<$ty> {
+                match endian {
+                    Endian::Little => self.[<read_ $ty>]::<byteorder::LittleEndian>(),
+                    Endian::Big => self.[<read_ $ty>]::<byteorder::BigEndian>(),
+                }
+            }
+        }
+    };
+}
+
+#[macro_export]
+/// generates peek_type_endian functions.
+macro_rules! peek_type_endian {
+    ($ty:ty) => {
+        paste! {
+            #[doc = "Seeks to position from start of the stream and reads a `" $ty "` type in a byte order chosen at runtime then returns to original position" ]
+            fn [<peek_ $ty _endian>](&mut self, position: u64, endian: Endian) -> std::io::Result<$ty> {
+                match endian {
+                    Endian::Little => self.[<peek_ $ty>]::<byteorder::LittleEndian>(position),
+                    Endian::Big => self.[<peek_ $ty>]::<byteorder::BigEndian>(position),
+                }
+            }
+        }
+    };
+}
@@ -0,0 +1,141 @@
+use std::io::{Read, Seek, SeekFrom};
+use crate::address::AddressMap;
+use crate::error::InterpreterError;
+
+/// A range of virtual addresses backed by a range of the file, like a PE section or an ELF segment.
+/// Addresses past `file_size` and before `va_size` read as zeroes, as `.bss` does.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Segment {
+    pub va_start: u64,
+    pub va_size: u64,
+    pub file_offset: u64,
+    pub file_size: u64,
+}
+
+impl Segment {
+    pub fn new(va_start: u64, va_size: u64, file_offset: u64, file_size: u64) -> Segment {
+        Segment { va_start, va_size, file_offset, file_size }
+    }
+
+    pub fn va_end(&self) -> u64 {
+        self.va_start + self.va_size
+    }
+
+    pub fn contains(&self, va: u64) -> bool {
+        (self.va_start..self.va_end()).contains(&va)
+    }
+
+    fn backed_size(&self) -> u64 {
+        self.file_size.min(self.va_size)
+    }
+}
+
+/// Finds the segment containing `va` in segments sorted by `va_start`.
+fn find_segment(segments: &[Segment], va: u64) -> Option<&Segment> {
+    let index = segments.partition_point(|s| s.va_start <= va);
+    segments[..index].iter().rev().find(|s| s.contains(va))
+}
+
+/// Maps a virtual address to the file offset backing it. Zero-filled tails have no file offset.
+impl AddressMap for [Segment] {
+    fn to_offset(&self, va: u64) -> Option<u64> {
+        let segment = find_segment(self, va)?;
+        let delta = va - segment.va_start;
+        (delta < segment.backed_size()).then(|| segment.file_offset + delta)
+    }
+
+    fn to_va(&self, offset: u64) -> Option<u64> {
+        self.iter()
+            .find(|s| (s.file_offset..s.file_offset + s.backed_size()).contains(&offset))
+            .map(|s| s.va_start + (offset - s.file_offset))
+    }
+}
+
+/// Wraps a `Read + Seek` file and addresses it by virtual address through a list of segments, so every
+/// `BinaryReader`/`BinaryPeeker` method takes VAs and pointers read from the file can be followed directly.
+///
+/// Reading an unmapped address fails with an `io::Error` wrapping `InterpreterError::UnmappedAddress`.
+/// `SeekFrom::End` is relative to the end of the highest segment.
+pub struct VaReader<R> {
+    inner: R,
+    segments: Vec<Segment>,
+    position: u64,
+}
+
+impl<R: Read + Seek> VaReader<R> {
+    pub fn new(inner: R, mut segments: Vec<Segment>) -> Self {
+        segments.sort_by_key(|s| s.va_start);
+        let position = segments.first().map_or(0, |s| s.va_start);
+        VaReader { inner, segments, position }
+    }
+
+    /// Segments sorted by virtual address. Also usable as an `AddressMap` over the wrapped file.
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    pub fn add_segment(&mut self, segment: Segment) {
+        let index = self.segments.partition_point(|s| s.va_start <= segment.va_start);
+        self.segments.insert(index, segment);
+    }
+
+    pub fn is_mapped(&self, va: u64) -> bool {
+        find_segment(&self.segments, va).is_some()
+    }
+
+    /// File offset backing `va`, or `None` if it is unmapped or zero-filled.
+    pub fn file_offset(&self, va: u64) -> Option<u64> {
+        self.segments.to_offset(va)
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read + Seek> Read for VaReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let va = self.position;
+        let segment = *find_segment(&self.segments, va)
+            .ok_or_else(|| std::io::Error::other(InterpreterError::UnmappedAddress(va)))?;
+        let delta = va - segment.va_start;
+        let read = if delta < segment.backed_size() {
+            let size = buf.len().min((segment.backed_size() - delta) as usize);
+            self.inner.seek(SeekFrom::Start(segment.file_offset + delta))?;
+            self.inner.read(&mut buf[..size])?
+        } else {
+            let size = buf.len().min((segment.va_size - delta) as usize);
+            buf[..size].fill(0);
+            size
+        };
+        self.position += read as u64;
+        Ok(read)
+    }
+}
+
+impl<R: Read + Seek> Seek for VaReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let (base, delta) = match pos {
+            SeekFrom::Start(position) => {
+                self.position = position;
+                return Ok(position);
+            }
+            SeekFrom::Current(delta) => (self.position, delta),
+            SeekFrom::End(delta) => (self.segments.iter().map(Segment::va_end).max().unwrap_or(0), delta),
+        };
+        self.position = base.checked_add_signed(delta)
+            .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "invalid seek to a negative or overflowing position"))?;
+        Ok(self.position)
+    }
+}
@@ -0,0 +1,245 @@
+use std::fmt::Debug;
+use std::marker::PhantomData;
+use std::mem::size_of;
+use std::ops::Range;
+use byteorder::ByteOrder;
+use crate::binary_reader::BinaryPeeker;
+use crate::error::InterpreterError;
+use crate::pod::{EndianPod, from_bytes};
+
+const CHUNK_SIZE: u64 = 1024 * 1024;
+
+/// A primitive a `ValueScan` can look for.
+pub trait ScanValue: EndianPod + PartialOrd + Debug {
+    /// `self + delta`, wrapping for integers.
+    fn add_delta(self, delta: Self) -> Self;
+}
+
+macro_rules! scan_int {
+    ($($ty:ty),*) => {
+        $(
+            impl ScanValue for $ty {
+                fn add_delta(self, delta: Self) -> Self {
+                    self.wrapping_add(delta)
+                }
+            }
+        )*
+    };
+}
+
+scan_int!(u8, i8, u16, i16, u32, i32, u64, i64, u128, i128);
+
+impl ScanValue for f32 {
+    fn add_delta(self, delta: Self) -> Self {
+        self + delta
+    }
+}
+
+impl ScanValue for f64 {
+    fn add_delta(self, delta: Self) -> Self {
+        self + delta
+    }
+}
+
+/// What the first scan keeps.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FirstScan<T> {
+    Exact(T),
+    /// Inclusive on both ends.
+    Between(T, T),
+    /// Every slot, to be narrowed down by comparing with later values.
+    Unknown,
+}
+
+/// What a next scan keeps, comparing each candidate's current value with the one seen by the previous scan.
+/// `Changed` and `Unchanged` compare the raw bytes, so they also work for NaN floats.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NextScan<T> {
+    Changed,
+    Unchanged,
+    Increased,
+    Decreased,
+    IncreasedBy(T),
+    DecreasedBy(T),
+    Exact(T),
+    Between(T, T),
+}
+
+impl<T: ScanValue> FirstScan<T> {
+    fn keeps(&self, value: T) -> bool {
+        match *self {
+            FirstScan::Exact(wanted) => value == wanted,
+            FirstScan::Between(low, high) => low <= value && value <= high,
+            FirstScan::Unknown => true,
+        }
+    }
+}
+
+impl<T: ScanValue> NextScan<T> {
+    fn keeps(&self, old: &[u8], new: &[u8], old_value: T, value: T) -> bool {
+        match *self {
+            NextScan::Changed => old != new,
+            NextScan::Unchanged => old == new,
+            NextScan::Increased => value > old_value,
+            NextScan::Decreased => value < old_value,
+            NextScan::IncreasedBy(delta) => value == old_value.add_delta(delta),
+            NextScan::DecreasedBy(delta) => old_value == value.add_delta(delta),
+            NextScan::Exact(wanted) => value == wanted,
+            NextScan::Between(low, high) => low <= value && value <= high,
+        }
+    }
+}
+
+/// Candidates within one chunk of a scanned range.
+#[derive(Clone, Debug)]
+enum Hits {
+    /// Every aligned slot in `values`, which holds the raw bytes of the chunk.
+    Every,
+    /// Offsets from the block base. `values` holds the candidates' bytes back to back.
+    Offsets(Vec<u32>),
+}
+
+#[derive(Clone, Debug)]
+struct Block {
+    base: u64,
+    hits: Hits,
+    values: Vec<u8>,
+}
+
+impl Block {
+    fn len(&self, size: usize, align: u64) -> usize {
+        match &self.hits {
+            Hits::Every => (self.values.len() + 1).saturating_sub(size).div_ceil(align as usize),
+            Hits::Offsets(offsets) => offsets.len(),
+        }
+    }
+
+    /// Offset from `base` and stored bytes of each candidate.
+    fn iter(&self, size: usize, align: u64) -> impl Iterator<Item = (u32, &[u8])> + '_ {
+        let count = self.len(size, align);
+        (0..count).map(move |i| match &self.hits {
+            Hits::Every => {
+                let offset = i * align as usize;
+                (offset as u32, &self.values[offset..offset + size])
+            }
+            Hits::Offsets(offsets) => (offsets[i], &self.values[i * size..(i + 1) * size]),
+        })
+    }
+}
+
+/// A Cheat Engine style scan for `T` values stored in `E` order, narrowed down by repeated next scans.
+///
+/// Works over any `BinaryPeeker`: a `ProcessMemory` with the ranges of its selected regions, a `VaReader`
+/// with its segments, or a plain file. Candidates are kept per 1 MiB chunk, as the chunk's raw bytes after an
+/// unknown initial value scan and as 32-bit offsets plus the last seen value afterwards, so millions of them
+/// stay cheap. Chunks that can no longer be read drop their candidates.
+#[derive(Clone, Debug)]
+pub struct ValueScan<T, E> {
+    blocks: Vec<Block>,
+    align: u64,
+    _marker: PhantomData<(T, E)>,
+}
+
+impl<T: ScanValue, E: ByteOrder> ValueScan<T, E> {
+    /// Scans `ranges` for slots aligned to `align` bytes whose value matches `condition`.
+    pub fn first_scan<R, I>(reader: &mut R, ranges: I, align: u64, condition: FirstScan<T>) -> Result<Self, InterpreterError>
+    where
+        R: BinaryPeeker + ?Sized,
+        I: IntoIterator<Item = Range<u64>>,
+    {
+        let size = size_of::<T>() as u64;
+        let align = align.max(1);
+        // Blocks start on aligned addresses, and hold the slots starting within `step` bytes of their base.
+        let step = CHUNK_SIZE.next_multiple_of(align);
+        let mut blocks = Vec::new();
+        for range in ranges {
+            let mut base = range.start.next_multiple_of(align);
+            while base.saturating_add(size) <= range.end {
+                let len = (step + size - 1).min(range.end - base);
+                let values = match reader.peek_bytes(base, len as usize) {
+                    Ok(values) => values,
+                    Err(e) => {
+                        log::debug!("skipping unreadable chunk at {:#x}: {}", base, e);
+                        base += step;
+                        continue;
+                    }
+                };
+                let block = match condition {
+                    FirstScan::Unknown => Block { base, hits: Hits::Every, values },
+                    _ => {
+                        let mut offsets = Vec::new();
+                        let mut kept = Vec::new();
+                        for offset in (0..values.len() as u64 + 1 - size).step_by(align as usize) {
+                            let bytes = &values[offset as usize..(offset + size) as usize];
+                            if condition.keeps(from_bytes::<T, E>(bytes)) {
+                                offsets.push(offset as u32);
+                                kept.extend_from_slice(bytes);
+                            }
+                        }
+                        Block { base, hits: Hits::Offsets(offsets), values: kept }
+                    }
+                };
+                if block.len(size as usize, align) > 0 {
+                    blocks.push(block);
+                }
+                base += step;
+            }
+        }
+        Ok(ValueScan { blocks, align, _marker: PhantomData })
+    }
+
+    /// Re-reads every candidate and keeps those matching `condition`.
+    pub fn next_scan<R: BinaryPeeker + ?Sized>(&mut self, reader: &mut R, condition: NextScan<T>) -> Result<(), InterpreterError> {
+        let size = size_of::<T>();
+        let mut blocks = Vec::with_capacity(self.blocks.len());
+        for block in &self.blocks {
+            let span = match &block.hits {
+                Hits::Every => block.values.len() as u64,
+                Hits::Offsets(offsets) => match (offsets.first(), offsets.last()) {
+                    (Some(&first), Some(&last)) => (last - first) as u64 + size as u64,
+                    _ => continue,
+                },
+            };
+            let first = block.iter(size, self.align).next().map_or(0, |(offset, _)| offset);
+            let current = match reader.peek_bytes(block.base + first as u64, span as usize) {
+                Ok(current) => current,
+                Err(e) => {
+                    log::debug!("dropping candidates at {:#x}: {}", block.base, e);
+                    continue;
+                }
+            };
+            let mut offsets = Vec::new();
+            let mut kept = Vec::new();
+            for (offset, old) in block.iter(size, self.align) {
+                let at = (offset - first) as usize;
+                let new = &current[at..at + size];
+                if condition.keeps(old, new, from_bytes::<T, E>(old), from_bytes::<T, E>(new)) {
+                    offsets.push(offset);
+                    kept.extend_from_slice(new);
+                }
+            }
+            if !offsets.is_empty() {
+                blocks.push(Block { base: block.base, hits: Hits::Offsets(offsets), values: kept });
+            }
+        }
+        self.blocks = blocks;
+        Ok(())
+    }
+
+    /// Number of candidates left.
+    pub fn len(&self) -> u64 {
+        self.blocks.iter().map(|b| b.len(size_of::<T>(), self.align) as u64).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    /// Address and last seen value of every candidate, in address order.
+    pub fn iter(&self) -> impl Iterator<Item = (u64, T)> + '_ {
+        self.blocks.iter().flat_map(move |block| {
+            block.iter(size_of::<T>(), self.align)
+                .map(move |(offset, bytes)| (block.base + offset as u64, from_bytes::<T, E>(bytes)))
+        })
+    }
+}